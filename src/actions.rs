@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    prelude::*,
+    ecs::system::SystemParam,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    storage,
+    gamepad::GamepadInput,
+};
+
+const STORAGE_KEY: &str = "bindings";
+
+/// Something the player does, whatever input it is bound to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    Pop,
+    /// Pops under the reticle from the keyboard, for player two.
+    ReticlePop,
+    Pause,
+    Retry,
+    BackToTitle,
+    Start,
+    NextLevel,
+    Leaderboard,
+    Settings,
+    /// Saves the replay on the results screens, plays it back on the title screen.
+    Replay,
+    /// Mode on the title screen and the leaderboard.
+    PreviousMode,
+    NextMode,
+    /// Difficulty on the title screen and the leaderboard.
+    PreviousDifficulty,
+    NextDifficulty,
+    /// Erases the last digit of the seed typed on the title screen.
+    EraseSeedDigit,
+}
+
+impl Action {
+    pub const ALL: [Action; 15] = [
+        Action::Pop,
+        Action::ReticlePop,
+        Action::Pause,
+        Action::Retry,
+        Action::BackToTitle,
+        Action::Start,
+        Action::NextLevel,
+        Action::Leaderboard,
+        Action::Settings,
+        Action::Replay,
+        Action::PreviousMode,
+        Action::NextMode,
+        Action::PreviousDifficulty,
+        Action::NextDifficulty,
+        Action::EraseSeedDigit,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Pop => "ボールを消す",
+            Action::ReticlePop => "照準でボールを消す",
+            Action::Pause => "ポーズ",
+            Action::Retry => "リトライ",
+            Action::BackToTitle => "タイトルに戻る",
+            Action::Start => "スタート",
+            Action::NextLevel => "つぎのレベルへ",
+            Action::Leaderboard => "ランキング",
+            Action::Settings => "せってい",
+            Action::Replay => "リプレイ",
+            Action::PreviousMode => "まえのモード",
+            Action::NextMode => "つぎのモード",
+            Action::PreviousDifficulty => "やさしくする",
+            Action::NextDifficulty => "むずかしくする",
+            Action::EraseSeedDigit => "シードを1けたけす",
+        }
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        match self {
+            Action::Pop => vec![
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButtonType::South),
+            ],
+            Action::ReticlePop => vec![
+                Binding::Key(KeyCode::Space),
+            ],
+            Action::Pause => vec![
+                Binding::Key(KeyCode::Escape),
+                Binding::Key(KeyCode::KeyP),
+                Binding::Gamepad(GamepadButtonType::Start),
+            ],
+            Action::Retry => vec![
+                Binding::Key(KeyCode::KeyR),
+                Binding::Gamepad(GamepadButtonType::Start),
+            ],
+            Action::BackToTitle => vec![
+                Binding::Key(KeyCode::KeyB),
+                Binding::Gamepad(GamepadButtonType::Select),
+            ],
+            Action::Start => vec![
+                Binding::Mouse(MouseButton::Left),
+                Binding::Gamepad(GamepadButtonType::Start),
+                Binding::Gamepad(GamepadButtonType::South),
+            ],
+            Action::NextLevel => vec![
+                Binding::Key(KeyCode::KeyN),
+                Binding::Gamepad(GamepadButtonType::South),
+            ],
+            Action::Leaderboard => vec![
+                Binding::Key(KeyCode::KeyL),
+            ],
+            Action::Settings => vec![
+                Binding::Key(KeyCode::KeyS),
+            ],
+            Action::Replay => vec![
+                Binding::Key(KeyCode::KeyV),
+            ],
+            Action::PreviousMode => vec![
+                Binding::Key(KeyCode::ArrowUp),
+                Binding::Gamepad(GamepadButtonType::DPadUp),
+            ],
+            Action::NextMode => vec![
+                Binding::Key(KeyCode::ArrowDown),
+                Binding::Gamepad(GamepadButtonType::DPadDown),
+            ],
+            Action::PreviousDifficulty => vec![
+                Binding::Key(KeyCode::ArrowLeft),
+                Binding::Gamepad(GamepadButtonType::DPadLeft),
+            ],
+            Action::NextDifficulty => vec![
+                Binding::Key(KeyCode::ArrowRight),
+                Binding::Gamepad(GamepadButtonType::DPadRight),
+            ],
+            Action::EraseSeedDigit => vec![
+                Binding::Key(KeyCode::Backspace),
+            ],
+        }
+    }
+}
+
+/// One input an action can be bound to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(KeyCode::ArrowUp) => "Key[↑]".to_string(),
+            Binding::Key(KeyCode::ArrowDown) => "Key[↓]".to_string(),
+            Binding::Key(KeyCode::ArrowLeft) => "Key[←]".to_string(),
+            Binding::Key(KeyCode::ArrowRight) => "Key[→]".to_string(),
+            Binding::Key(key) => {
+                let name = format!("{:?}", key);
+                let name = name.strip_prefix("Key").or(name.strip_prefix("Digit")).unwrap_or(&name);
+                format!("Key[{}]", name)
+            },
+            Binding::Mouse(MouseButton::Left) => "左クリック".to_string(),
+            Binding::Mouse(MouseButton::Right) => "右クリック".to_string(),
+            Binding::Mouse(MouseButton::Middle) => "中クリック".to_string(),
+            Binding::Mouse(button) => format!("Mouse[{:?}]", button),
+            Binding::Gamepad(button_type) => format!("Pad[{:?}]", button_type),
+        }
+    }
+}
+
+/// Inputs bound to every action, persisted through `storage`.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActionMap(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        Self(Action::ALL.iter().map(|action| (*action, action.default_bindings())).collect())
+    }
+}
+
+impl ActionMap {
+    /// The saved bindings, with defaults for actions that were never saved.
+    fn load() -> Self {
+        let mut action_map = Self::default();
+        let saved: Option<ActionMap> = storage::load(STORAGE_KEY);
+        action_map.0.extend(saved.into_iter().flat_map(|saved| saved.0));
+        action_map
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, self);
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Binds `action` to `binding`, replacing the bindings of the same device:
+    /// keyboard and mouse are one device, gamepads the other.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.0.entry(action).or_default();
+        bindings.retain(|bound| bound.is_gamepad() != binding.is_gamepad());
+        bindings.push(binding);
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.0.insert(action, action.default_bindings());
+    }
+
+    /// Keyboard or mouse input of `action` for on-screen hints.
+    pub fn hint(&self, action: Action) -> String {
+        self.bindings(action)
+            .iter()
+            .find(|binding| !binding.is_gamepad())
+            .or(self.bindings(action).first())
+            .map(Binding::label)
+            .unwrap_or_default()
+    }
+}
+
+/// Actions pressed this frame, read through the `ActionMap`.
+///
+/// Inputs whose plugin is missing never count as pressed, so systems using
+/// it also run headless.
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    action_map: Res<'w, ActionMap>,
+    keyboard_input: Option<Res<'w, ButtonInput<KeyCode>>>,
+    mouse_input: Option<Res<'w, ButtonInput<MouseButton>>>,
+    gamepad: GamepadInput<'w>,
+}
+
+impl ActionInput<'_> {
+    fn binding_just_pressed(&self, binding: &Binding) -> bool {
+        match binding {
+            Binding::Key(key) => self.keyboard_input.as_ref().is_some_and(|input| input.just_pressed(*key)),
+            Binding::Mouse(button) => self.mouse_input.as_ref().is_some_and(|input| input.just_pressed(*button)),
+            Binding::Gamepad(button_type) => self.gamepad.just_pressed(*button_type),
+        }
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.action_map.bindings(action).iter().any(|binding| self.binding_just_pressed(binding))
+    }
+
+    /// Whether `action` was pressed on the keyboard or mouse, aiming with the cursor.
+    pub fn pointer_just_pressed(&self, action: Action) -> bool {
+        self.action_map.bindings(action)
+            .iter()
+            .any(|binding| !binding.is_gamepad() && self.binding_just_pressed(binding))
+    }
+
+    /// Whether `action` was pressed on a gamepad, aiming with the reticle.
+    pub fn gamepad_just_pressed(&self, action: Action) -> bool {
+        self.action_map.bindings(action)
+            .iter()
+            .any(|binding| binding.is_gamepad() && self.binding_just_pressed(binding))
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+) {
+    println!("actions: setup");
+    commands.insert_resource(ActionMap::load());
+}
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ActionMap>()
+            .add_systems(Startup, setup)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_has_default_bindings() {
+        let action_map = ActionMap::default();
+
+        for action in Action::ALL {
+            assert!(!action_map.bindings(action).is_empty(), "{:?}", action);
+        }
+    }
+
+    #[test]
+    fn rebinding_replaces_only_the_same_device() {
+        let mut action_map = ActionMap::default();
+
+        action_map.rebind(Action::Retry, Binding::Key(KeyCode::Space));
+        assert_eq!(action_map.bindings(Action::Retry), &[
+            Binding::Gamepad(GamepadButtonType::Start),
+            Binding::Key(KeyCode::Space),
+        ]);
+
+        action_map.rebind(Action::Retry, Binding::Gamepad(GamepadButtonType::North));
+        assert_eq!(action_map.bindings(Action::Retry), &[
+            Binding::Key(KeyCode::Space),
+            Binding::Gamepad(GamepadButtonType::North),
+        ]);
+
+        action_map.reset(Action::Retry);
+        assert_eq!(action_map.bindings(Action::Retry), Action::Retry.default_bindings());
+    }
+
+    #[test]
+    fn bindings_survive_saving() {
+        let mut action_map = ActionMap::default();
+        action_map.rebind(Action::Pop, Binding::Mouse(MouseButton::Right));
+
+        let json = serde_json::to_string(&action_map).unwrap();
+        let loaded: ActionMap = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded, action_map);
+    }
+
+    #[test]
+    fn hints_prefer_keyboard_and_mouse() {
+        let action_map = ActionMap::default();
+
+        assert_eq!(action_map.hint(Action::Retry), "Key[R]");
+        assert_eq!(action_map.hint(Action::Pop), "左クリック");
+        assert_eq!(action_map.hint(Action::Pause), "Key[Escape]");
+        assert_eq!(action_map.hint(Action::Settings), "Key[S]");
+        assert_eq!(Binding::Key(KeyCode::Digit1).label(), "Key[1]");
+        assert_eq!(action_map.hint(Action::NextMode), "Key[↓]");
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CURSOR_RANGE,
+    level::{Layout, Level},
+};
+
+/// Preset chosen on the title screen, scaling every level of the run.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Insane,
+}
+
+/// How a difficulty changes a level, as factors of the level's own values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preset {
+    pub ball_count: f32,
+    pub speed: f32,
+    pub size: f32,
+    pub time_limit: f32,
+    /// How far outside a ball or button a click still hits it, in pixels.
+    pub cursor_range: f32,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Insane,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Insane => "insane",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "かんたん",
+            Difficulty::Normal => "ふつう",
+            Difficulty::Hard => "むずかしい",
+            Difficulty::Insane => "げきむず",
+        }
+    }
+
+    pub fn preset(self) -> Preset {
+        match self {
+            Difficulty::Easy => Preset {
+                ball_count: 0.75,
+                speed: 0.7,
+                size: 1.25,
+                time_limit: 1.3,
+                cursor_range: CURSOR_RANGE * 2.0,
+            },
+            Difficulty::Normal => Preset {
+                ball_count: 1.0,
+                speed: 1.0,
+                size: 1.0,
+                time_limit: 1.0,
+                cursor_range: CURSOR_RANGE,
+            },
+            Difficulty::Hard => Preset {
+                ball_count: 1.25,
+                speed: 1.3,
+                size: 0.85,
+                time_limit: 0.9,
+                cursor_range: CURSOR_RANGE * 0.5,
+            },
+            Difficulty::Insane => Preset {
+                ball_count: 1.5,
+                speed: 1.6,
+                size: 0.7,
+                time_limit: 0.8,
+                cursor_range: 0.0,
+            },
+        }
+    }
+
+    pub fn cursor_range(self) -> f32 {
+        self.preset().cursor_range
+    }
+
+    /// `level` as played at this difficulty.
+    pub fn apply(self, level: &Level) -> Level {
+        let preset = self.preset();
+        let mut ball_count = ((level.ball_count as f32 * preset.ball_count).round() as usize).max(1);
+        // custom layouts have no room for more balls than positions
+        if let Layout::Custom(positions) = &level.layout { ball_count = ball_count.min(positions.len()) }
+
+        Level {
+            ball_count,
+            speed: level.speed.scale(preset.speed),
+            size: level.size.scale(preset.size),
+            time_limit: level.time_limit * preset.time_limit,
+            ..level.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::Spread;
+
+    #[test]
+    fn normal_plays_the_level_as_written() {
+        let level = Level::default();
+
+        assert_eq!(Difficulty::Normal.apply(&level), level);
+        assert_eq!(Difficulty::Normal.cursor_range(), CURSOR_RANGE);
+    }
+
+    #[test]
+    fn harder_presets_make_more_faster_smaller_balls() {
+        let level = Level {
+            speed: Spread::Range(100.0, 200.0),
+            ..Default::default()
+        };
+        let easy = Difficulty::Easy.apply(&level);
+        let insane = Difficulty::Insane.apply(&level);
+
+        assert!(easy.ball_count < level.ball_count && level.ball_count < insane.ball_count);
+        assert!(easy.speed.max() < insane.speed.max());
+        assert!(easy.size.max() > insane.size.max());
+        assert!(easy.time_limit > insane.time_limit);
+        assert_eq!(insane.speed, Spread::Range(160.0, 320.0));
+        assert!(Difficulty::Easy.cursor_range() > Difficulty::Insane.cursor_range());
+    }
+
+    #[test]
+    fn custom_layouts_keep_their_positions() {
+        let level = Level {
+            ball_count: 2,
+            layout: Layout::Custom(vec![(0.0, 0.0), (100.0, 0.0)]),
+            ..Default::default()
+        };
+
+        assert_eq!(Difficulty::Insane.apply(&level).ball_count, 2);
+        assert_eq!(Difficulty::Easy.apply(&level).ball_count, 2);
+    }
+
+    #[test]
+    fn difficulties_are_saved_by_name() {
+        assert_eq!(serde_json::to_string(&Difficulty::Hard).unwrap(), "\"hard\"");
+    }
+}
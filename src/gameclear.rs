@@ -1,34 +1,60 @@
 use bevy::prelude::*;
 
 use crate::{
-    WINDOW_SIZE,
-    GAMETIME_LIMIT,
     PATH_FONT,
     AppState,
-    Config,
+    GameMode,
     GameTimer,
+    Player,
+    Score,
+    actions::{Action, ActionInput, ActionMap},
+    highscore::NameEntry,
+    ingame::{Splits, Tallies},
+    level::LevelProgress,
+    playarea::PlayArea,
 };
 
 const GAMECLEAR_TEXT: &str = "ゲームクリア";
 const GAMECLEAR_SIZE: f32 = 32.0;
 const TIMER_TEXT: &str = "クリアタイム: ";
-const RETRY_TEXT: &str = "リトライ: Key[R]";
-const BACKTOTITLE_TEXT: &str = "タイトルに戻る: Key[B]";
+const SCORE_TEXT: &str = "スコア: ";
+const TIMEBONUS_TEXT: &str = " (タイムボーナス +";
+const SPLITS_TEXT: &str = "スプリット";
+const PERSONALBEST_TEXT: &str = " じこベスト!";
+const SPLITS_SIZE: f32 = 16.0;
+// every this many pops gets a row in the split table, and the last one
+const SPLITS_STEP: usize = 5;
 const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const HEADER_COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+const AHEAD_COLOR: Color = Color::srgb(0.4, 0.9, 0.4);
+const BEHIND_COLOR: Color = Color::srgb(0.9, 0.4, 0.4);
 const TEXT_SIZE: f32 = 20.0;
 const TEXT_PADDING: f32 = 50.0;
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
 
 #[derive(Component)]
 struct Gameclear;
 
+/// Next level, retry and back to title lines, tappable on touch screens.
+#[derive(Component, Debug, Clone, Copy)]
+enum GameclearButton {
+    NextLevel,
+    Retry,
+    BackToTitle,
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
     timer: Res<GameTimer>,
+    score: Res<Score>,
+    progress: Res<LevelProgress>,
+    action_map: Res<ActionMap>,
 ) {
     println!("gameclear: setup");
     // gameover
-    let top = WINDOW_SIZE.y / 2.0 - GAMECLEAR_SIZE / 2.0 - TEXT_PADDING * 1.5;
+    let top = play_area.size.y / 2.0 - GAMECLEAR_SIZE / 2.0 - TEXT_PADDING * 1.5;
 
     commands.spawn((
         TextBundle::from_section(
@@ -49,8 +75,8 @@ fn setup(
     ))
     .insert(Name::new("gameclear"));
     // timer
-    let top = WINDOW_SIZE.y / 2.0 - TEXT_SIZE / 2.0 - TEXT_PADDING * 0.5;
-    let cleartime = GAMETIME_LIMIT - (timer.0.remaining_secs() * 100.0).round() / 100.0;
+    let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0 - TEXT_PADDING * 0.5;
+    let cleartime = timer.clear_time();
 
     commands.spawn((
         TextBundle::from_section(
@@ -70,12 +96,17 @@ fn setup(
         Gameclear,
     ))
     .insert(Name::new("timer"));
-    // retry
-    let top = WINDOW_SIZE.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 0.5;
+    // score
+    let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0;
 
     commands.spawn((
         TextBundle::from_section(
-            RETRY_TEXT, 
+            // time-attack rounds have no time limit to earn a bonus from
+            if score.time_bonus > 0 {
+                format!("{}{}{}{})", SCORE_TEXT, score.points, TIMEBONUS_TEXT, score.time_bonus)
+            } else {
+                format!("{}{}", SCORE_TEXT, score.points)
+            },
             TextStyle {
                 font: asset_server.load(PATH_FONT),
                 font_size: TEXT_SIZE,
@@ -90,13 +121,63 @@ fn setup(
         }),
         Gameclear,
     ))
+    .insert(Name::new("score"));
+    // next level
+    if progress.has_next() {
+        let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 0.75;
+
+        commands.spawn((
+            TextBundle::from_section(
+                format!("{}: {}", Action::NextLevel.label(), action_map.hint(Action::NextLevel)),
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: TEXT_COLOR,
+                }
+            )
+            .with_style(Style {
+                position_type: PositionType::Relative,
+                justify_self: JustifySelf::Center,
+                top: Val::Px(top),
+                ..Default::default()
+            })
+            .with_background_color(BUTTON_COLOR),
+            Interaction::default(),
+            GameclearButton::NextLevel,
+            Gameclear,
+        ))
+        .insert(Name::new("nextlevel"));
+    }
+    // retry
+    let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 1.25;
+
+    commands.spawn((
+        TextBundle::from_section(
+            format!("{}: {}", Action::Retry.label(), action_map.hint(Action::Retry)),
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: TEXT_SIZE,
+                color: TEXT_COLOR,
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top: Val::Px(top),
+            ..Default::default()
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        GameclearButton::Retry,
+        Gameclear,
+    ))
     .insert(Name::new("retry"));
     // back to title
-    let top = WINDOW_SIZE.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 1.5;
+    let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 1.75;
 
     commands.spawn((
         TextBundle::from_section(
-            BACKTOTITLE_TEXT, 
+            format!("{}: {}", Action::BackToTitle.label(), action_map.hint(Action::BackToTitle)),
             TextStyle {
                 font: asset_server.load(PATH_FONT),
                 font_size: TEXT_SIZE,
@@ -108,33 +189,126 @@ fn setup(
             justify_self: JustifySelf::Center,
             top: Val::Px(top),
             ..Default::default()
-        }),
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        GameclearButton::BackToTitle,
         Gameclear,
     ))
     .insert(Name::new("backtotitle"));
 }
 
 fn update(
-    mut config: ResMut<Config>,
     mut next_state: ResMut<NextState<AppState>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut progress: ResMut<LevelProgress>,
+    button_query: Query<(&Interaction, &GameclearButton), Changed<Interaction>>,
+    actions: ActionInput,
 ) {
     let mut closure = |app_state: AppState| {
-        println!("gameclear: config setup ingame is true");
-        config.setup_ingame = true;
         println!("gameclear: moved state to {:?} from Gameclear", app_state);
         next_state.set(app_state);
     };
 
-    for key in keyboard_input.get_just_pressed() {
-        match key {
-            KeyCode::KeyR => closure(AppState::Ingame),
-            KeyCode::KeyB => closure(AppState::Mainmenu),
-            _ => {},
+    let pressed = button_query.iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button);
+    let action_buttons = [
+        (Action::NextLevel, GameclearButton::NextLevel),
+        (Action::Retry, GameclearButton::Retry),
+        (Action::BackToTitle, GameclearButton::BackToTitle),
+    ];
+    let action_pressed = action_buttons.into_iter()
+        .filter(|(action, _)| actions.just_pressed(*action))
+        .map(|(_, button)| button);
+
+    for button in action_pressed.chain(pressed) {
+        match button {
+            GameclearButton::NextLevel if progress.has_next() => {
+                progress.advance();
+                println!("gameclear: advanced to level {}", progress.number());
+                closure(AppState::Ingame);
+            },
+            GameclearButton::NextLevel => {},
+            GameclearButton::Retry => closure(AppState::Ingame),
+            GameclearButton::BackToTitle => closure(AppState::Mainmenu),
         }
     }
 }
 
+/// Pops the split table has a row for.
+fn split_rows(len: usize) -> impl Iterator<Item = usize> {
+    (0..len).filter(move |index| (index + 1) % SPLITS_STEP == 0 || index + 1 == len)
+}
+
+fn setup_splits(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    splits: Res<Splits>,
+) {
+    let text_style = |color| TextStyle {
+        font: asset_server.load(PATH_FONT),
+        font_size: SPLITS_SIZE,
+        color,
+    };
+    let header = if splits.is_best() { format!("{}{}", SPLITS_TEXT, PERSONALBEST_TEXT) } else { SPLITS_TEXT.to_string() };
+    let mut sections = vec![TextSection::new(header, text_style(HEADER_COLOR))];
+
+    for index in split_rows(splits.times.len()) {
+        sections.push(TextSection::new(
+            format!("\n{:>2}: {:.2}", index + 1, splits.times[index]),
+            text_style(TEXT_COLOR),
+        ));
+        let Some(delta) = splits.delta(index) else { continue };
+        sections.push(TextSection::new(
+            format!(" ({:+.2})", delta),
+            text_style(if delta < 0.0 { AHEAD_COLOR } else { BEHIND_COLOR }),
+        ));
+    }
+    // split table
+    commands.spawn((
+        TextBundle::from_sections(sections)
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(TEXT_PADDING * 0.5),
+                left: Val::Px(TEXT_PADDING * 0.5),
+                ..Default::default()
+            }),
+        Gameclear,
+    ))
+    .insert(Name::new("splits"));
+}
+
+/// Winner of a two-player round above the title, in their color.
+fn setup_winner(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+    tallies: Res<Tallies>,
+) {
+    let text_style = |color| TextStyle {
+        font: asset_server.load(PATH_FONT),
+        font_size: TEXT_SIZE,
+        color,
+    };
+    let winner_color = tallies.winner().map_or(TEXT_COLOR, Player::color);
+    let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0 - TEXT_PADDING * 2.5;
+    // winner
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(tallies.winner_text(), text_style(winner_color)),
+            TextSection::new(format!(" ({})", tallies.text()), text_style(TEXT_COLOR)),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top: Val::Px(top),
+            ..Default::default()
+        }),
+        Gameclear,
+    ))
+    .insert(Name::new("winner"));
+}
+
 fn despawn(
     mut commands: Commands,
     query: Query<Entity, With<Gameclear>>,
@@ -149,8 +323,26 @@ impl Plugin for GameclearPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems(OnEnter(AppState::Gameclear), setup)
-            .add_systems(Update, update.run_if(in_state(AppState::Gameclear)))
+            .add_systems(OnEnter(AppState::Gameclear), setup_splits
+                .run_if(resource_equals(GameMode::TimeAttack)))
+            .add_systems(OnEnter(AppState::Gameclear), setup_winner
+                .run_if(resource_exists::<Tallies>))
+            .add_systems(Update, update
+                .run_if(in_state(AppState::Gameclear))
+                .run_if(not(resource_exists::<NameEntry>)))
             .add_systems(OnExit(AppState::Gameclear), despawn)
         ;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_table_shows_every_fifth_pop_and_the_last() {
+        assert_eq!(split_rows(12).collect::<Vec<_>>(), vec![4, 9, 11]);
+        assert_eq!(split_rows(10).collect::<Vec<_>>(), vec![4, 9]);
+        assert_eq!(split_rows(0).count(), 0);
+    }
+}
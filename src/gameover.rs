@@ -1,37 +1,63 @@
 use bevy::prelude::*;
 
 use crate::{
-    WINDOW_SIZE,
     PATH_FONT,
     AppState,
-    Config,
     BallCount,
+    GameMode,
+    GameTimer,
+    Score,
+    actions::{Action, ActionInput, ActionMap},
+    highscore::NameEntry,
+    ingame::Tallies,
+    playarea::PlayArea,
 };
 
 const GAMEOVER_TEXT: &str = "ゲームオーバー";
 const GAMEOVER_SIZE: f32 = 32.0;
+const ENDLESS_TEXT: &str = "しゅうりょう";
 const BALLCOUNT_TEXT: &str = "のこったボールのかず: ";
-const RETRY_TEXT: &str = "リトライ: Key[R]";
-const BACKTOTITLE_TEXT: &str = "タイトルに戻る: Key[B]";
+const SURVIVAL_TEXT: &str = "いきのこったじかん: ";
+const SECONDS_TEXT: &str = "びょう";
+const SCORE_TEXT: &str = "スコア: ";
 const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 const TEXT_SIZE: f32 = 20.0;
 const TEXT_PADDING: f32 = 50.0;
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
 
 #[derive(Component)]
 struct Gameover;
 
+/// Retry and back to title lines, tappable on touch screens.
+#[derive(Component, Debug, Clone, Copy)]
+enum GameoverButton {
+    Retry,
+    BackToTitle,
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    ball_count: Res<BallCount>,
+    play_area: Res<PlayArea>,
+    (ball_count, timer): (Res<BallCount>, Res<GameTimer>),
+    score: Res<Score>,
+    action_map: Res<ActionMap>,
+    (mode, tallies): (Res<GameMode>, Option<Res<Tallies>>),
 ) {
     println!("gameover: setup");
+    // endless rounds always end by overflowing, so they show the time survived
+    let (title, result) = match (*mode, tallies) {
+        (GameMode::Endless, _) => (ENDLESS_TEXT.to_string(), format!("{}{:.2}{}", SURVIVAL_TEXT, timer.clear_time(), SECONDS_TEXT)),
+        // two-player rounds end with the winner instead
+        (GameMode::Versus, Some(tallies)) => (tallies.winner_text(), tallies.text()),
+        _ => (GAMEOVER_TEXT.to_string(), format!("{}{}", BALLCOUNT_TEXT, **ball_count)),
+    };
     // gameover
-    let top = Val::Px(WINDOW_SIZE.y / 2.0 - GAMEOVER_SIZE / 2.0 - TEXT_PADDING * 1.5);
+    let top = Val::Px(play_area.size.y / 2.0 - GAMEOVER_SIZE / 2.0 - TEXT_PADDING * 1.5);
 
     commands.spawn((
         TextBundle::from_section(
-            GAMEOVER_TEXT,
+            title,
             TextStyle {
                 font: asset_server.load(PATH_FONT),
                 font_size: GAMEOVER_SIZE,
@@ -48,11 +74,11 @@ fn setup(
     ))
     .insert(Name::new("gameover"));
     // ballcount
-    let top = Val::Px(WINDOW_SIZE.y / 2.0 - TEXT_SIZE / 2.0 - TEXT_PADDING * 0.5);
+    let top = Val::Px(play_area.size.y / 2.0 - TEXT_SIZE / 2.0 - TEXT_PADDING * 0.5);
 
     commands.spawn((
         TextBundle::from_section(
-            format!("{}{}", BALLCOUNT_TEXT, **ball_count), 
+            result,
             TextStyle {
                 font: asset_server.load(PATH_FONT),
                 font_size: TEXT_SIZE,
@@ -68,12 +94,12 @@ fn setup(
         Gameover,
     ))
     .insert(Name::new("ballcount"));
-    // retry
-    let top = Val::Px(WINDOW_SIZE.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 0.5);
+    // score
+    let top = Val::Px(play_area.size.y / 2.0 - TEXT_SIZE / 2.0);
 
     commands.spawn((
         TextBundle::from_section(
-            RETRY_TEXT, 
+            format!("{}{}", SCORE_TEXT, score.points),
             TextStyle {
                 font: asset_server.load(PATH_FONT),
                 font_size: TEXT_SIZE,
@@ -88,13 +114,37 @@ fn setup(
         }),
         Gameover,
     ))
+    .insert(Name::new("score"));
+    // retry
+    let top = Val::Px(play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING);
+
+    commands.spawn((
+        TextBundle::from_section(
+            format!("{}: {}", Action::Retry.label(), action_map.hint(Action::Retry)),
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: TEXT_SIZE,
+                color: TEXT_COLOR,
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top,
+            ..Default::default()
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        GameoverButton::Retry,
+        Gameover,
+    ))
     .insert(Name::new("retry"));
     // back to title
-    let top = Val::Px(WINDOW_SIZE.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 1.5);
+    let top = Val::Px(play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 1.5);
 
     commands.spawn((
         TextBundle::from_section(
-            BACKTOTITLE_TEXT, 
+            format!("{}: {}", Action::BackToTitle.label(), action_map.hint(Action::BackToTitle)),
             TextStyle {
                 font: asset_server.load(PATH_FONT),
                 font_size: TEXT_SIZE,
@@ -106,29 +156,40 @@ fn setup(
             justify_self: JustifySelf::Center,
             top,
             ..Default::default()
-        }),
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        GameoverButton::BackToTitle,
         Gameover,
     ))
     .insert(Name::new("backtotitle"));
 }
 
 fn update(
-    mut config: ResMut<Config>,
     mut next_state: ResMut<NextState<AppState>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    button_query: Query<(&Interaction, &GameoverButton), Changed<Interaction>>,
+    actions: ActionInput,
 ) {
     let mut closure = |app_state: AppState| {
-        println!("gameover: change config.setup_ingame to true");
-        config.setup_ingame = true;
         println!("gameover: moved state to {:?} from Gameover", app_state);
         next_state.set(app_state);
     };
 
-    for key in keyboard_input.get_just_pressed() {
-        match key {
-            KeyCode::KeyR => closure(AppState::Ingame),
-            KeyCode::KeyB => closure(AppState::Mainmenu),
-            _ => {},
+    let pressed = button_query.iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button);
+    let action_buttons = [
+        (Action::Retry, GameoverButton::Retry),
+        (Action::BackToTitle, GameoverButton::BackToTitle),
+    ];
+    let action_pressed = action_buttons.into_iter()
+        .filter(|(action, _)| actions.just_pressed(*action))
+        .map(|(_, button)| button);
+
+    for button in action_pressed.chain(pressed) {
+        match button {
+            GameoverButton::Retry => closure(AppState::Ingame),
+            GameoverButton::BackToTitle => closure(AppState::Mainmenu),
         }
     }
 }
@@ -147,7 +208,9 @@ impl Plugin for GameoverPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems(OnEnter(AppState::Gameover), setup)
-            .add_systems(Update, update.run_if(in_state(AppState::Gameover)))
+            .add_systems(Update, update
+                .run_if(in_state(AppState::Gameover))
+                .run_if(not(resource_exists::<NameEntry>)))
             .add_systems(OnExit(AppState::Gameover), despawn)
         ;
     }
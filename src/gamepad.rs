@@ -0,0 +1,41 @@
+use bevy::{
+    prelude::*,
+    ecs::system::SystemParam,
+};
+
+/// Buttons and sticks of every connected gamepad.
+///
+/// Reads nothing when the input plugin is missing, so systems using it
+/// also run headless.
+#[derive(SystemParam)]
+pub struct GamepadInput<'w> {
+    gamepads: Option<Res<'w, Gamepads>>,
+    buttons: Option<Res<'w, ButtonInput<GamepadButton>>>,
+    axes: Option<Res<'w, Axis<GamepadAxis>>>,
+}
+
+impl GamepadInput<'_> {
+    fn gamepads(&self) -> impl Iterator<Item = Gamepad> + '_ {
+        self.gamepads.iter().flat_map(|gamepads| gamepads.iter())
+    }
+
+    /// Whether `button_type` was pressed this frame on any gamepad.
+    pub fn just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        let Some(buttons) = &self.buttons else { return false };
+        self.gamepads().any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    }
+
+    /// Left stick of all gamepads combined, at most one long.
+    pub fn left_stick(&self) -> Vec2 {
+        let Some(axes) = &self.axes else { return Vec2::ZERO };
+        let axis = |gamepad, axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.0);
+
+        self.gamepads()
+            .map(|gamepad| Vec2::new(
+                axis(gamepad, GamepadAxisType::LeftStickX),
+                axis(gamepad, GamepadAxisType::LeftStickY),
+            ))
+            .sum::<Vec2>()
+            .clamp_length_max(1.0)
+    }
+}
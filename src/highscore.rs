@@ -0,0 +1,450 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    prelude::*,
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    PATH_FONT,
+    storage,
+    AppState,
+    GameMode,
+    BallCount,
+    GameTimer,
+    difficulty::Difficulty,
+    ingame::Splits,
+    level::CurrentLevel,
+    playarea::PlayArea,
+};
+
+const STORAGE_KEY: &str = "highscores";
+const SPLITS_STORAGE_KEY: &str = "splits";
+pub const TABLE_LEN: usize = 5;
+const NAME_LEN: usize = 8;
+const NAME_DEFAULT: &str = "ななし";
+const NEWRECORD_TEXT: &str = "しんきろく! なまえ: ";
+const CURSOR_TEXT: &str = "_";
+const CONFIRM_TEXT: &str = " Key[Enter] / タップ";
+const RANKED_TEXT: &str = "いに とうろくしました";
+const TEXT_COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+const TEXT_SIZE: f32 = 20.0;
+const TEXT_PADDING: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Table {
+    ClearTime,
+    RemainingBalls,
+    SurvivalTime,
+}
+
+impl Table {
+    /// Tables of `mode`, in the order the leaderboard shows them.
+    pub fn of(mode: GameMode) -> &'static [Table] {
+        match mode {
+            GameMode::Classic | GameMode::ColorMatch | GameMode::Sequence => &[Table::ClearTime, Table::RemainingBalls],
+            GameMode::Endless => &[Table::SurvivalTime],
+            GameMode::TimeAttack => &[Table::ClearTime],
+            // two players share a round, so neither keeps a record
+            GameMode::Versus => &[],
+        }
+    }
+
+    /// Whether `record` keeps its place ahead of `value`, ties going to the older record.
+    fn ranks_after(self, record: f32, value: f32) -> bool {
+        match self {
+            Table::ClearTime | Table::RemainingBalls => record <= value,
+            Table::SurvivalTime => record >= value,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub value: f32,
+}
+
+/// Best results of one game mode on one level and difficulty, best first:
+/// the lowest times and ball counts, the longest survival times.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct Leaderboard {
+    #[serde(default)]
+    clear_times: Vec<Record>,
+    #[serde(default)]
+    remaining_balls: Vec<Record>,
+    #[serde(default)]
+    survival_times: Vec<Record>,
+}
+
+impl Leaderboard {
+    pub fn records(&self, table: Table) -> &[Record] {
+        match table {
+            Table::ClearTime => &self.clear_times,
+            Table::RemainingBalls => &self.remaining_balls,
+            Table::SurvivalTime => &self.survival_times,
+        }
+    }
+
+    fn records_mut(&mut self, table: Table) -> &mut Vec<Record> {
+        match table {
+            Table::ClearTime => &mut self.clear_times,
+            Table::RemainingBalls => &mut self.remaining_balls,
+            Table::SurvivalTime => &mut self.survival_times,
+        }
+    }
+
+    /// Rank `value` would get in `table`, `None` if it does not make the table.
+    pub fn rank(&self, table: Table, value: f32) -> Option<usize> {
+        let records = self.records(table);
+        let rank = records.iter().take_while(|record| table.ranks_after(record.value, value)).count();
+
+        if rank < TABLE_LEN { Some(rank) } else { None }
+    }
+
+    pub fn insert(&mut self, table: Table, record: Record) -> Option<usize> {
+        let rank = self.rank(table, record.value)?;
+        let records = self.records_mut(table);
+
+        records.insert(rank, record);
+        records.truncate(TABLE_LEN);
+        Some(rank)
+    }
+}
+
+/// Leaderboards of every game mode, level and difficulty, persisted through
+/// `storage`.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct HighScores(BTreeMap<String, Leaderboard>);
+
+impl HighScores {
+    fn key(mode: GameMode, level: &str, difficulty: Difficulty) -> String {
+        format!("{}/{}", mode.key(), BestSplits::key(level, difficulty))
+    }
+
+    /// The saved leaderboards, without those saved before they were kept per
+    /// level and difficulty: their records cannot be told apart.
+    fn load() -> Self {
+        let mut highscores: HighScores = storage::load(STORAGE_KEY).unwrap_or_default();
+        highscores.0.retain(|key, _| key.contains('/'));
+        highscores
+    }
+
+    pub fn leaderboard(&self, mode: GameMode, level: &str, difficulty: Difficulty) -> Leaderboard {
+        self.0.get(&Self::key(mode, level, difficulty)).cloned().unwrap_or_default()
+    }
+
+    fn leaderboard_mut(&mut self, mode: GameMode, level: &str, difficulty: Difficulty) -> &mut Leaderboard {
+        self.0.entry(Self::key(mode, level, difficulty)).or_default()
+    }
+}
+
+/// Splits of the fastest time-attack clear of every level and difficulty,
+/// persisted through `storage`.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct BestSplits(BTreeMap<String, Vec<f32>>);
+
+impl BestSplits {
+    fn key(level: &str, difficulty: Difficulty) -> String {
+        format!("{}/{}", level, difficulty.key())
+    }
+
+    pub fn get(&self, level: &str, difficulty: Difficulty) -> Option<&Vec<f32>> {
+        self.0.get(&Self::key(level, difficulty))
+    }
+
+    /// Keeps `splits` if they finish faster than the stored ones.
+    pub fn offer(&mut self, level: &str, difficulty: Difficulty, splits: &[f32]) -> bool {
+        let Some(time) = splits.last() else { return false };
+        let best = self.get(level, difficulty).and_then(|best| best.last());
+        if best.is_some_and(|best| best <= time) { return false }
+
+        self.0.insert(Self::key(level, difficulty), splits.to_vec());
+        true
+    }
+}
+
+/// A new record waiting for the player's name.
+#[derive(Resource, Debug)]
+pub struct NameEntry {
+    table: Table,
+    value: f32,
+    name: String,
+}
+
+#[derive(Component)]
+struct NameEntryUi;
+
+fn setup(
+    mut commands: Commands,
+) {
+    println!("highscore: setup");
+    commands.insert_resource(HighScores::load());
+    let best_splits: BestSplits = storage::load(SPLITS_STORAGE_KEY).unwrap_or_default();
+    commands.insert_resource(best_splits);
+}
+
+fn save_best_splits(
+    mut best_splits: ResMut<BestSplits>,
+    splits: Res<Splits>,
+    level: Res<CurrentLevel>,
+    difficulty: Res<Difficulty>,
+) {
+    if !best_splits.offer(&level.name, *difficulty, &splits.times) { return }
+
+    println!("highscore: new personal best splits");
+    storage::save(SPLITS_STORAGE_KEY, &*best_splits);
+}
+
+fn check_clear_record(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+    highscores: Res<HighScores>,
+    (mode, level, difficulty): (Res<GameMode>, Res<CurrentLevel>, Res<Difficulty>),
+    timer: Res<GameTimer>,
+) {
+    let leaderboard = highscores.leaderboard(*mode, &level.name, *difficulty);
+    let value = timer.clear_time();
+    start_name_entry(commands, asset_server, play_area, &leaderboard, *mode, Table::ClearTime, value);
+}
+
+fn check_gameover_record(
+    commands: Commands,
+    asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+    highscores: Res<HighScores>,
+    (mode, level, difficulty): (Res<GameMode>, Res<CurrentLevel>, Res<Difficulty>),
+    (ball_count, timer): (Res<BallCount>, Res<GameTimer>),
+) {
+    let (table, value) = match *mode {
+        GameMode::Endless => (Table::SurvivalTime, timer.clear_time()),
+        // a round without a single pop is no record
+        _ if **ball_count >= level.ball_count => return,
+        _ => (Table::RemainingBalls, **ball_count as f32),
+    };
+    let leaderboard = highscores.leaderboard(*mode, &level.name, *difficulty);
+    start_name_entry(commands, asset_server, play_area, &leaderboard, *mode, table, value);
+}
+
+fn start_name_entry(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+    leaderboard: &Leaderboard,
+    mode: GameMode,
+    table: Table,
+    value: f32,
+) {
+    if !Table::of(mode).contains(&table) { return }
+    if leaderboard.rank(table, value).is_none() { return }
+
+    println!("highscore: new record {} in {:?}", value, table);
+    commands.insert_resource(NameEntry { table, value, name: String::new() });
+
+    let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 2.5;
+    let style = TextStyle {
+        font: asset_server.load(PATH_FONT),
+        font_size: TEXT_SIZE,
+        color: TEXT_COLOR,
+    };
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(NEWRECORD_TEXT, style.clone()),
+            TextSection::from_style(style.clone()),
+            TextSection::new(CURSOR_TEXT, style.clone()),
+            TextSection::new(CONFIRM_TEXT, style),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top: Val::Px(top),
+            ..Default::default()
+        }),
+        // tapping the line confirms the name on touch screens
+        Interaction::default(),
+        NameEntryUi,
+    ))
+    .insert(Name::new("nameentry"));
+}
+
+fn enter_name(
+    mut commands: Commands,
+    mut entry: ResMut<NameEntry>,
+    mut highscores: ResMut<HighScores>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut query: Query<(&mut Text, &Interaction), With<NameEntryUi>>,
+    (mode, level, difficulty): (Res<GameMode>, Res<CurrentLevel>, Res<Difficulty>),
+) {
+    let Ok((mut text, interaction)) = query.get_single_mut() else { return };
+    let mut confirmed = *interaction == Interaction::Pressed;
+
+    for event in keyboard_events.read() {
+        if event.state != ButtonState::Pressed { continue }
+
+        match &event.logical_key {
+            Key::Enter => confirmed = true,
+            Key::Backspace => { entry.name.pop(); },
+            Key::Space => push_name(&mut entry.name, " "),
+            Key::Character(chars) => push_name(&mut entry.name, chars),
+            _ => {},
+        }
+    }
+
+    if !confirmed {
+        text.sections[1].value = entry.name.clone();
+        return
+    }
+
+    let name = match entry.name.trim() {
+        "" => NAME_DEFAULT.to_string(),
+        name => name.to_string(),
+    };
+    let record = Record { name, value: entry.value };
+    let rank = highscores.leaderboard_mut(*mode, &level.name, *difficulty).insert(entry.table, record);
+    println!("highscore: saved rank {:?} in {:?}", rank, entry.table);
+    storage::save(STORAGE_KEY, &*highscores);
+    commands.remove_resource::<NameEntry>();
+
+    if let Some(rank) = rank {
+        text.sections[0].value = format!("{}{}", rank + 1, RANKED_TEXT);
+        for section in text.sections.iter_mut().skip(1) { section.value.clear() }
+    }
+}
+
+fn push_name(name: &mut String, chars: &str) {
+    for c in chars.chars().filter(|c| !c.is_control()) {
+        if name.chars().count() >= NAME_LEN { break }
+        name.push(c);
+    }
+}
+
+fn despawn(
+    mut commands: Commands,
+    query: Query<Entity, With<NameEntryUi>>,
+) {
+    println!("highscore: despawn");
+    commands.remove_resource::<NameEntry>();
+    for entity in query.iter() { commands.entity(entity).despawn() }
+}
+
+pub struct HighscorePlugin;
+
+impl Plugin for HighscorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup)
+            .add_systems(OnEnter(AppState::Gameclear), check_clear_record)
+            .add_systems(OnEnter(AppState::Gameclear), save_best_splits
+                .run_if(resource_equals(GameMode::TimeAttack)))
+            .add_systems(OnEnter(AppState::Gameover), check_gameover_record)
+            .add_systems(Update, enter_name.run_if(resource_exists::<NameEntry>))
+            .add_systems(OnExit(AppState::Gameclear), despawn)
+            .add_systems(OnExit(AppState::Gameover), despawn)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(value: f32) -> Record {
+        Record { name: NAME_DEFAULT.to_string(), value }
+    }
+
+    #[test]
+    fn records_are_sorted_lowest_first() {
+        let mut leaderboard = Leaderboard::default();
+
+        assert_eq!(leaderboard.insert(Table::ClearTime, record(12.0)), Some(0));
+        assert_eq!(leaderboard.insert(Table::ClearTime, record(8.5)), Some(0));
+        assert_eq!(leaderboard.insert(Table::ClearTime, record(10.0)), Some(1));
+
+        let values: Vec<f32> = leaderboard.records(Table::ClearTime)
+            .iter()
+            .map(|record| record.value)
+            .collect();
+        assert_eq!(values, vec![8.5, 10.0, 12.0]);
+        assert!(leaderboard.records(Table::RemainingBalls).is_empty());
+    }
+
+    #[test]
+    fn full_table_keeps_only_the_best() {
+        let mut leaderboard = Leaderboard::default();
+
+        for value in 1..=TABLE_LEN { leaderboard.insert(Table::RemainingBalls, record(value as f32)); }
+
+        assert_eq!(leaderboard.rank(Table::RemainingBalls, TABLE_LEN as f32), None);
+        assert_eq!(leaderboard.insert(Table::RemainingBalls, record(0.0)), Some(0));
+        assert_eq!(leaderboard.records(Table::RemainingBalls).len(), TABLE_LEN);
+        assert_eq!(leaderboard.records(Table::RemainingBalls).last(), Some(&record(TABLE_LEN as f32 - 1.0)));
+    }
+
+    #[test]
+    fn ties_rank_after_existing_records() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.insert(Table::ClearTime, record(10.0));
+
+        assert_eq!(leaderboard.rank(Table::ClearTime, 10.0), Some(1));
+    }
+
+    #[test]
+    fn survival_times_are_sorted_longest_first() {
+        let mut leaderboard = Leaderboard::default();
+
+        assert_eq!(leaderboard.insert(Table::SurvivalTime, record(30.0)), Some(0));
+        assert_eq!(leaderboard.insert(Table::SurvivalTime, record(45.5)), Some(0));
+        assert_eq!(leaderboard.insert(Table::SurvivalTime, record(30.0)), Some(2));
+
+        let values: Vec<f32> = leaderboard.records(Table::SurvivalTime)
+            .iter()
+            .map(|record| record.value)
+            .collect();
+        assert_eq!(values, vec![45.5, 30.0, 30.0]);
+    }
+
+    #[test]
+    fn leaderboards_are_kept_per_mode_level_and_difficulty() {
+        let mut highscores = HighScores::default();
+        highscores.leaderboard_mut(GameMode::Classic, "1", Difficulty::Normal).insert(Table::ClearTime, record(10.0));
+
+        let json = serde_json::to_string(&highscores).unwrap();
+        let loaded: HighScores = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.leaderboard(GameMode::Classic, "1", Difficulty::Normal).records(Table::ClearTime), &[record(10.0)]);
+        assert!(loaded.leaderboard(GameMode::Classic, "2", Difficulty::Normal).records(Table::ClearTime).is_empty());
+        assert!(loaded.leaderboard(GameMode::Classic, "1", Difficulty::Hard).records(Table::ClearTime).is_empty());
+        assert!(loaded.leaderboard(GameMode::TimeAttack, "1", Difficulty::Normal).records(Table::ClearTime).is_empty());
+    }
+
+    #[test]
+    fn only_faster_splits_replace_the_best() {
+        let mut best_splits = BestSplits::default();
+
+        assert!(best_splits.offer("1", Difficulty::Normal, &[1.0, 3.0]));
+        assert!(!best_splits.offer("1", Difficulty::Normal, &[0.5, 3.5]));
+        assert!(best_splits.offer("1", Difficulty::Hard, &[2.0, 4.0]));
+        assert!(best_splits.offer("1", Difficulty::Normal, &[1.5, 2.5]));
+        assert!(!best_splits.offer("1", Difficulty::Normal, &[]));
+
+        assert_eq!(best_splits.get("1", Difficulty::Normal), Some(&vec![1.5, 2.5]));
+        assert_eq!(best_splits.get("1", Difficulty::Hard), Some(&vec![2.0, 4.0]));
+        assert_eq!(best_splits.get("2", Difficulty::Normal), None);
+    }
+
+    #[test]
+    fn names_are_limited() {
+        let mut name = String::new();
+
+        push_name(&mut name, "いっとく\n");
+        push_name(&mut name, "nvim1234");
+
+        assert_eq!(name, "いっとくnvim");
+    }
+}
@@ -1,187 +1,543 @@
+use std::{collections::VecDeque, time::Duration};
+
 use bevy::{
     prelude::*,
-    sprite::MaterialMesh2dBundle,
-    window::PrimaryWindow,
+    ecs::{query::QueryData, system::SystemParam},
+    sprite::Mesh2dHandle,
+};
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
 };
-use rand::distributions::{Distribution, Uniform};
 
+use super::{
+    ClickSet,
+    PhysicsSet,
+    RestartRound,
+    broadphase::{grid_contacts, Body},
+    collision::{resolve, Particle},
+    colormatch::{TargetColor, WRONG_COLOR_PENALTY},
+    picking::{BallClicked, Pickable},
+    sequence::{NextNumber, Number, WRONG_ORDER_PENALTY},
+};
 use crate::{
-    WINDOW_SIZE,
-    CURSOR_RANGE,
-    BALL_COUNT,
+    PATH_FONT,
     PATH_SOUND_DESPAWN,
     AppState,
-    Config,
+    Round,
     BallCount,
+    GameMode,
+    GameTimer,
+    Player,
+    Seed,
+    GameRng,
+    level::{BallKind, CurrentLevel},
+    playarea::PlayArea,
 };
 
+const BALL_RESTITUTION: f32 = 1.0;
+const LABEL_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+const NEXT_LABEL_COLOR: Color = Color::srgb(0.9, 0.1, 0.1);
+// font size of a label relative to the ball size
+const LABEL_SIZE: f32 = 0.6;
+/// Colors of the color-match mode with their names, apart from the colors
+/// of the special kinds.
+pub const PALETTE: [(Color, &str); 5] = [
+    (Color::srgb(0.25, 0.45, 1.0), "あお"),
+    (Color::srgb(1.0, 0.9, 0.2), "きいろ"),
+    (Color::srgb(0.65, 0.35, 0.9), "むらさき"),
+    (Color::srgb(1.0, 0.5, 0.75), "ピンク"),
+    (Color::srgb(0.95, 0.95, 0.95), "しろ"),
+];
+
 #[derive(Component)]
-struct Ball;
+pub struct Ball;
+
+/// Position of a ball after the latest physics step.
+#[derive(Component, Deref, DerefMut, Debug)]
+pub struct Position(Vec2);
+
+/// Position of a ball before the latest physics step, to interpolate from.
+#[derive(Component, Deref, DerefMut, Debug)]
+struct PreviousPosition(Vec2);
 
 #[derive(Component, Deref, DerefMut, Debug)]
 struct Velocity(Vec2);
 
+/// Mass of a ball, growing with its area.
+#[derive(Component, Deref, Debug)]
+struct Mass(f32);
+
+/// Share of the approach speed kept after a collision, 1.0 being perfectly elastic.
+#[derive(Component, Deref, Debug)]
+struct Restitution(f32);
+
+/// Color of a ball, copied into its material when rendering.
+#[derive(Component, Deref, DerefMut, Debug)]
+pub struct BallColor(Color);
+
+/// Clicks a tough ball still takes before it pops.
+#[derive(Component, Deref, DerefMut, Debug)]
+struct Health(u32);
+
+/// Text on a special ball, telling its kind or remaining health.
+#[derive(Component)]
+struct BallLabel;
+
 #[derive(Resource, Deref)]
 struct DespawnSound(Handle<AudioSource>);
 
-#[derive(Event, Default)]
-struct DespawnEvent;
+/// A ball popped by a click or a bomb, with the speed, size and kind it had.
+#[derive(Event, Debug)]
+pub struct BallPopped {
+    pub speed: f32,
+    pub size: f32,
+    pub kind: BallKind,
+    /// Player whose click popped it, also through a bomb.
+    pub player: Player,
+}
+
+/// Special kinds keep their color, normal balls change it on every bounce.
+fn kind_color(kind: BallKind) -> Option<Color> {
+    match kind {
+        BallKind::Normal => None,
+        BallKind::Tough(_) => Some(Color::srgb(0.6, 0.6, 0.65)),
+        BallKind::Splitter => Some(Color::srgb(0.3, 0.8, 0.9)),
+        BallKind::Bomb(_) => Some(Color::srgb(0.9, 0.5, 0.1)),
+        BallKind::BonusTime(_) => Some(Color::srgb(0.3, 0.9, 0.3)),
+        BallKind::PenaltyTime(_) => Some(Color::srgb(0.9, 0.2, 0.2)),
+    }
+}
+
+/// Everything the label of a ball shows.
+#[derive(QueryData)]
+struct LabelQuery {
+    kind: &'static BallKind,
+    health: Option<&'static Health>,
+    number: Option<&'static Number>,
+}
 
-const SIZE: Vec3 = Vec3::new(30.0, 30.0, 0.0);
-const VELOCITY: f32 = 2.0;
-const SPEED: f32 = 120.0;
+/// Text on a ball: its number in a number-sequence round, else its kind.
+fn ball_label(ball: &LabelQueryItem) -> String {
+    if let Some(number) = ball.number { return number.to_string() }
 
-fn setup(
+    match *ball.kind {
+        BallKind::Normal => String::new(),
+        BallKind::Tough(hp) => ball.health.map_or(hp, |health| **health).to_string(),
+        BallKind::Splitter => "S".to_string(),
+        BallKind::Bomb(_) => "B".to_string(),
+        BallKind::BonusTime(_) => "+".to_string(),
+        BallKind::PenaltyTime(_) => "-".to_string(),
+    }
+}
+
+/// Components of a ball of diameter `size` at `pos`, the spawn index being z.
+pub(super) fn ball_bundle(pos: Vec3, velocity: Vec2, size: f32, kind: BallKind, color: Color) -> impl Bundle {
+    (
+        SpatialBundle::from_transform(
+            Transform::from_translation(pos).with_scale(Vec3::new(size, size, 1.0))
+        ),
+        Ball,
+        kind,
+        Pickable::ball(),
+        Position(pos.truncate()),
+        PreviousPosition(pos.truncate()),
+        Velocity(velocity),
+        Mass(size.powi(2)),
+        Restitution(BALL_RESTITUTION),
+        BallColor(color),
+    )
+}
+
+fn restart(
+    _trigger: Trigger<RestartRound>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    asset_server: Res<AssetServer>,
-    config: Res<Config>,
-    ball_count: Res<BallCount>,
+    mut rng: ResMut<GameRng>,
+    mut ball_count: ResMut<BallCount>,
+    query: Query<Entity, With<Ball>>,
+    (level, play_area, mode): (Res<CurrentLevel>, Res<PlayArea>, Res<GameMode>),
+    seed: Res<Seed>,
 ) {
-    if !config.setup_ingame { return };
+    // balls of the round being restarted
+    for entity in query.iter() { commands.entity(entity).despawn_recursive() }
+
+    println!("balls: setup with seed {}", **seed);
+    *rng = GameRng::new(*seed);
+    // spaced for the largest balls
+    let ball_positions = level.layout.positions(level.ball_count, level.size.max());
 
-    println!("balls: setup");
-    // despawn sound
+    if ball_positions.len() < level.ball_count { error!("ball_positions is not long enough.") }
+    let kinds = level.ball_kinds(ball_positions.len(), &mut **rng);
+    **ball_count = kinds.iter().filter(|kind| kind.counts()).count();
+    let mut numbered = 0;
+
+    for (ball_pos, kind) in ball_positions.into_iter().zip(kinds) {
+        let size = level.size.sample(&mut **rng);
+        let speed = level.speed.sample(&mut **rng);
+        let die_velocity = Uniform::new_inclusive(-speed, speed);
+        let ball_pos = play_area.clamp(ball_pos.truncate(), size).extend(ball_pos.z);
+        let velocity = Vec2::new(
+            die_velocity.sample(&mut **rng),
+            die_velocity.sample(&mut **rng),
+        );
+        let color = ball_color(&mut **rng, *mode);
+        let mut ball = commands.spawn(ball_bundle(ball_pos, velocity, size, kind, color));
+
+        ball.insert(Name::new("ball"));
+        if let BallKind::Tough(hp) = kind { ball.insert(Health(hp)); }
+        // the balls that must be popped to clear, numbered in spawn order
+        if *mode == GameMode::Sequence && kind.counts() {
+            numbered += 1;
+            ball.insert(Number(numbered));
+        }
+    }
+}
+
+fn setup_sound(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    println!("balls: setup sound");
     let despawn_sound = asset_server.load(PATH_SOUND_DESPAWN);
     commands.insert_resource(DespawnSound(despawn_sound));
-    // balls
-    let mut rng = rand::thread_rng();
-    let ball_positions = set_ball_positions();
-    let die_velocity = Uniform::from(-VELOCITY..VELOCITY);
-
-    if ball_positions.len() < **ball_count { error!("ball_positions is not long enough.") }
-
-    for i in 0..**ball_count {
-        let ball_pos = ball_positions[i];
-        let velocity_pos = Vec2::new(
-            die_velocity.sample(&mut rng),
-            die_velocity.sample(&mut rng),
-        );
+}
+
+fn add_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(Entity, &BallColor, &BallKind), Added<Ball>>,
+) {
+    for (entity, color, kind) in query.iter() {
+        commands.entity(entity).try_insert((
+            Mesh2dHandle(meshes.add(Circle::default())),
+            materials.add(ColorMaterial::from(kind_color(*kind).unwrap_or(**color))),
+        ));
+    }
+}
 
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(Circle::default()).into(),
-                material: materials.add(ColorMaterial::from(random_color())),
-                transform: Transform::from_translation(ball_pos).with_scale(SIZE),
+fn add_label(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query: Query<(Entity, LabelQuery, &Transform), Added<Ball>>,
+) {
+    for (entity, ball, transform) in query.iter() {
+        if *ball.kind == BallKind::Normal && ball.number.is_none() { continue }
+        let size = transform.scale.x;
+        // drawn at its font size, against the scale of the ball
+        let label = commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    ball_label(&ball),
+                    TextStyle {
+                        font: asset_server.load(PATH_FONT),
+                        font_size: size * LABEL_SIZE,
+                        color: LABEL_COLOR,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, 0.0, 0.1).with_scale(Vec3::splat(1.0 / size)),
                 ..Default::default()
             },
-            Ball,
-            Velocity(velocity_pos * SPEED),
+            BallLabel,
         ))
-        .insert(Name::new("ball"));
+        .insert(Name::new("label"))
+        .id();
+        commands.entity(entity).add_child(label);
+    }
+}
+
+fn update_label(
+    mut label_query: Query<&mut Text, With<BallLabel>>,
+    query: Query<(LabelQuery, &Children), Changed<Health>>,
+) {
+    for (ball, children) in query.iter() {
+        let mut labels = label_query.iter_many_mut(children);
+        while let Some(mut text) = labels.fetch_next() {
+            text.sections[0].value = ball_label(&ball);
+        }
+    }
+}
+
+/// Marks the ball to pop next in a number-sequence round.
+fn highlight_next(
+    mut label_query: Query<&mut Text, With<BallLabel>>,
+    query: Query<(&Number, &Children), With<Ball>>,
+    next_number: Res<NextNumber>,
+) {
+    for (number, children) in query.iter() {
+        let color = if **number == **next_number { NEXT_LABEL_COLOR } else { LABEL_COLOR };
+        let mut labels = label_query.iter_many_mut(children);
+        while let Some(mut text) = labels.fetch_next() {
+            if text.sections[0].style.color != color { text.sections[0].style.color = color }
+        }
+    }
+}
+
+fn update_material(
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(&BallColor, &BallKind, &Handle<ColorMaterial>), Changed<BallColor>>,
+) {
+    for (color, kind, handle) in query.iter() {
+        let Some(color_material) = materials.get_mut(handle.id()) else { continue };
+        color_material.color = kind_color(*kind).unwrap_or(**color);
     }
 }
 
 fn apply_velocity(
-    mut query: Query<(&mut Transform, &Velocity)>,
-    time_step: Res<Time<Fixed>>,
+    mut query: Query<(&mut Position, &mut PreviousPosition, &Velocity)>,
+    time: Res<Time>,
 ) {
-    for (mut transform, velocity) in &mut query {
-        transform.translation.x += velocity.x * time_step.delta().as_secs_f32();
-        transform.translation.y += velocity.y * time_step.delta().as_secs_f32();
+    for (mut position, mut previous_position, velocity) in &mut query {
+        **previous_position = **position;
+        **position += **velocity * time.delta_seconds();
+    }
+}
+
+/// Moves the rendered balls between the last two physics steps by how far
+/// the frame is into the next step.
+fn interpolate(
+    mut query: Query<(&mut Transform, &Position, &PreviousPosition)>,
+    time: Res<Time<Fixed>>,
+) {
+    let fraction = time.overstep_fraction();
+
+    for (mut transform, position, previous_position) in &mut query {
+        let translation = previous_position.lerp(**position, fraction);
+        transform.translation = translation.extend(transform.translation.z);
     }
 }
 
 fn check_wall_collisions(
-    mut query: Query<(&Handle<ColorMaterial>, &mut Velocity, &Transform), With<Ball>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(&mut BallColor, &mut Velocity, &mut Position, &Transform), With<Ball>>,
+    mut rng: ResMut<GameRng>,
+    play_area: Res<PlayArea>,
+    mode: Res<GameMode>,
 ) {
-    for (handle, mut velocity, transform) in query.iter_mut() {
-        let size = transform.scale.truncate();
-        let left_window_collision =
-            WINDOW_SIZE.x / 2.0 < transform.translation.x + size.x / 2.0;
-        let right_window_collision =
-            -WINDOW_SIZE.x / 2.0 > transform.translation.x - size.x / 2.0;
-        let top_window_collision =
-            WINDOW_SIZE.y / 2.0 < transform.translation.y + size.y / 2.0;
-        let bottom_window_collision =
-            -WINDOW_SIZE.y / 2.0 > transform.translation.y - size.y / 2.0;
-
-        if left_window_collision
-        || right_window_collision
-        || top_window_collision
-        || bottom_window_collision {
-            let color_material: &mut ColorMaterial = materials.get_mut(handle.id()).unwrap();
-            color_material.color = random_color();
-
-            if left_window_collision || right_window_collision { velocity.x = -velocity.x }
-            if top_window_collision || bottom_window_collision { velocity.y = -velocity.y }
+    for (mut color, mut velocity, mut position, transform) in query.iter_mut() {
+        let bound = play_area.half_size() - transform.scale.truncate() / 2.0;
+        let right_wall_collision = position.x > bound.x && velocity.x > 0.0;
+        let left_wall_collision = position.x < -bound.x && velocity.x < 0.0;
+        let top_wall_collision = position.y > bound.y && velocity.y > 0.0;
+        let bottom_wall_collision = position.y < -bound.y && velocity.y < 0.0;
+
+        if right_wall_collision
+        || left_wall_collision
+        || top_wall_collision
+        || bottom_wall_collision {
+            **color = ball_color(&mut **rng, *mode);
+
+            if right_wall_collision || left_wall_collision { velocity.x = -velocity.x }
+            if top_wall_collision || bottom_wall_collision { velocity.y = -velocity.y }
+        }
+
+        // balls left outside by a shrinking window are pushed back in
+        **position = play_area.clamp(**position, transform.scale.x);
+    }
+}
+
+/// Everything the collision response reads and writes on a ball.
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct CollisionQuery {
+    entity: Entity,
+    color: &'static mut BallColor,
+    velocity: &'static mut Velocity,
+    position: &'static mut Position,
+    transform: &'static Transform,
+    mass: &'static Mass,
+    restitution: &'static Restitution,
+}
+
+impl CollisionQueryItem<'_> {
+    fn particle(&self) -> Particle {
+        Particle {
+            position: **self.position,
+            velocity: **self.velocity,
+            mass: **self.mass,
+            restitution: **self.restitution,
+            size: self.transform.scale.x,
         }
     }
+
+    fn apply(&mut self, particle: &Particle) {
+        **self.position = particle.position;
+        **self.velocity = particle.velocity;
+    }
 }
 
 fn check_ball_collisions(
-    mut query: Query<(&Handle<ColorMaterial>, &mut Velocity, &Transform), With<Ball>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    time_step: Res<Time<Fixed>>,
+    mut query: Query<CollisionQuery, With<Ball>>,
+    mut rng: ResMut<GameRng>,
+    mode: Res<GameMode>,
 ) {
-    let mut combinations = query.iter_combinations_mut();
-
-    while let Some([ball1, ball2]) = combinations.fetch_next() {
-        let (handle_1, mut velocity_1, transform_1) = ball1;
-        let (handle_2, mut velocity_2, transform_2) = ball2;
-        let position_1 = transform_1.translation.truncate();
-        let position_2 = transform_2.translation.truncate();
-        let direction_1 = velocity_1.xy() * time_step.delta().as_secs_f32();
-        let direction_2 = velocity_2.xy() * time_step.delta().as_secs_f32();
-        let collision = (
-            (position_1.x + direction_1.x * 2.0 - position_2.x - direction_2.x * 2.0).powi(2) +
-            (position_1.y + direction_1.y * 2.0 - position_2.y - direction_2.y * 2.0).powi(2)
-        ) <= SIZE.x.powi(2);
-
-        if collision {
-            let color_material: &mut ColorMaterial = materials.get_mut(handle_1.id()).unwrap();
-            color_material.color = random_color();
-            let color_material: &mut ColorMaterial = materials.get_mut(handle_2.id()).unwrap();
-            color_material.color = random_color();
-
-            velocity_1.x += (direction_2.x - direction_1.x) / time_step.delta().as_secs_f32();
-            velocity_1.y += (direction_2.y - direction_1.y) / time_step.delta().as_secs_f32();
-            velocity_2.x += (direction_1.x - direction_2.x) / time_step.delta().as_secs_f32();
-            velocity_2.y += (direction_1.y - direction_2.y) / time_step.delta().as_secs_f32();
+    let (entities, bodies): (Vec<Entity>, Vec<Body>) = query
+        .iter()
+        .map(|ball| (ball.entity, Body {
+            position: **ball.position,
+            size: ball.transform.scale.x,
+        }))
+        .unzip();
+
+    for (i, j) in grid_contacts(&bodies) {
+        let Ok([mut ball1, mut ball2]) = query.get_many_mut([entities[i], entities[j]]) else { continue };
+        let mut particle1 = ball1.particle();
+        let mut particle2 = ball2.particle();
+
+        if resolve(&mut particle1, &mut particle2) {
+            **ball1.color = ball_color(&mut **rng, *mode);
+            **ball2.color = ball_color(&mut **rng, *mode);
         }
+
+        ball1.apply(&particle1);
+        ball2.apply(&particle2);
     }
 }
 
-fn mouse_click(
+/// Rules of the current mode on which balls a click may pop.
+#[derive(SystemParam)]
+struct PopRules<'w> {
+    target: Option<Res<'w, TargetColor>>,
+    next_number: Option<ResMut<'w, NextNumber>>,
+}
+
+impl PopRules<'_> {
+    /// Seconds off the time limit for clicking `ball` against the rules,
+    /// `None` when the click may pop it.
+    fn penalty(&self, ball: &PopQueryItem) -> Option<f32> {
+        if self.target.as_ref().is_some_and(|target| !target.matches(*ball.kind, **ball.color)) {
+            return Some(WRONG_COLOR_PENALTY)
+        }
+        if self.next_number.as_ref().is_some_and(|next_number| !next_number.allows(ball.number)) {
+            return Some(WRONG_ORDER_PENALTY)
+        }
+        None
+    }
+
+    /// Moves the order on past a popped ball, to the lowest number in `left`.
+    fn popped(&mut self, number: Option<Number>, left: impl Iterator<Item = Number>) {
+        let (Some(next_number), Some(_)) = (self.next_number.as_mut(), number) else { return };
+        next_number.advance(left.min());
+    }
+}
+
+/// Everything popping reads and changes on a ball.
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct PopQuery {
+    entity: Entity,
+    kind: &'static BallKind,
+    health: Option<&'static mut Health>,
+    position: &'static Position,
+    velocity: &'static Velocity,
+    transform: &'static Transform,
+    color: &'static BallColor,
+    number: Option<&'static Number>,
+}
+
+fn pop_clicked(
     mut commands: Commands,
-    mut despawn_events: EventWriter<DespawnEvent>,
+    mut clicked_events: EventReader<BallClicked>,
+    mut popped_events: EventWriter<BallPopped>,
     mut ball_count: ResMut<BallCount>,
-    mut next_state: ResMut<NextState<AppState>>,
-    mouse_events: Res<ButtonInput<MouseButton>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    ball_query: Query<(Entity, &Transform), With<Ball>>,
+    (mut timer, mut next_state): (ResMut<GameTimer>, ResMut<NextState<AppState>>),
+    mut ball_query: Query<PopQuery, With<Ball>>,
+    (mode, mut rules): (Res<GameMode>, PopRules),
 ) {
-    if !mouse_events.just_pressed(MouseButton::Left) { return }
+    // clicked balls first, then the ones caught by bombs, which pop outright
+    let mut pending: VecDeque<(Entity, Player, bool)> = clicked_events.read()
+        .map(|clicked| (clicked.entity, clicked.player, true))
+        .collect();
+    // in order, so a click over several numbered balls pops them all
+    pending.make_contiguous().sort_by_key(|(entity, ..)| ball_query.get(*entity).ok().and_then(|ball| ball.number.copied()));
+    let mut despawned = Vec::new();
+
+    while let Some((entity, player, clicked)) = pending.pop_front() {
+        if despawned.contains(&entity) { continue }
+        let Ok(mut ball) = ball_query.get_mut(entity) else { continue };
 
-    let window = window_query.single();
-    let mut cursor_pos = window.cursor_position().unwrap();
-    cursor_pos = Vec2::new(
-        cursor_pos.x - window.width() / 2.0, 
-        -cursor_pos.y + window.height() / 2.0,
-    );
+        // the wrong color or number only costs time, bombs pop anything
+        if let (true, Some(penalty)) = (clicked, rules.penalty(&ball)) {
+            println!("balls: clicked against the rules, took {} seconds off", penalty);
+            timer.0.tick(Duration::from_secs_f32(penalty));
+            continue
+        }
 
-    for (ball_entity, ball_transform) in ball_query.iter() {
-        let ball_pos = ball_transform.translation.truncate();
-        let distance = cursor_pos.distance(ball_pos);
+        if let (true, Some(health)) = (clicked, ball.health.as_mut()) {
+            if health.0 > 1 {
+                health.0 -= 1;
+                println!("balls: hit tough ball, {} clicks left", health.0);
+                continue
+            }
+        }
 
-        if distance < SIZE.x - CURSOR_RANGE {
+        let kind = *ball.kind;
+        let position = **ball.position;
+        let velocity = **ball.velocity;
+        let translation = ball.transform.translation;
+        let size = ball.transform.scale.x;
+        let color = **ball.color;
+        let number = ball.number.copied();
+
+        popped_events.send(BallPopped {
+            speed: velocity.length(),
+            size,
+            kind,
+            player,
+        });
+        commands.entity(entity).despawn_recursive();
+        despawned.push(entity);
+        let left = ball_query.iter()
+            .filter(|other| !despawned.contains(&other.entity))
+            .filter_map(|other| other.number.copied());
+        rules.popped(number, left);
+        if kind.counts() {
             println!("balls: despawn ball from {} to {}", **ball_count, **ball_count - 1);
-            despawn_events.send_default();
             **ball_count -= 1;
-            commands.entity(ball_entity).despawn();
-            if **ball_count <= 0 {
-                println!("balls: moved state to Gameclear from Ingame");
-                next_state.set(AppState::Gameclear);
-            }
+        }
+
+        match kind {
+            BallKind::Normal | BallKind::Tough(_) => {},
+            BallKind::Splitter => {
+                println!("balls: split ball");
+                for direction in [velocity.perp(), -velocity.perp()] {
+                    let offset = direction.normalize_or_zero() * size / 4.0;
+                    let pos = (position + offset).extend(translation.z);
+                    commands.spawn(ball_bundle(pos, direction, size / 2.0, BallKind::Normal, color))
+                        .insert(Name::new("ball"));
+                }
+                **ball_count += 2;
+            },
+            BallKind::Bomb(radius) => {
+                println!("balls: bomb exploded");
+                let caught = ball_query.iter()
+                    .filter(|other| other.position.distance(position) < radius)
+                    .map(|other| (other.entity, player, false));
+                pending.extend(caught);
+            },
+            BallKind::BonusTime(secs) => {
+                println!("balls: added {} seconds", secs);
+                let duration = timer.0.duration() + Duration::from_secs_f32(secs);
+                timer.0.set_duration(duration);
+            },
+            BallKind::PenaltyTime(secs) => {
+                if clicked {
+                    println!("balls: took {} seconds off", secs);
+                    timer.0.tick(Duration::from_secs_f32(secs));
+                }
+            },
         }
     }
+
+    // the endless mode keeps spawning balls into an empty field
+    if !despawned.is_empty() && **ball_count == 0 && *mode != GameMode::Endless {
+        println!("balls: moved state to Gameclear from Ingame");
+        next_state.set(AppState::Gameclear);
+    }
 }
 
 fn play_despawn_sound(
     mut commands: Commands,
-    mut events: EventReader<DespawnEvent>,
+    mut events: EventReader<BallPopped>,
     sound: Res<DespawnSound>,
 ) {
     if events.is_empty() { return }
@@ -192,19 +548,26 @@ fn play_despawn_sound(
     });
 }
 
+/// Hides the balls while paused, so a pause can not be used to aim.
+fn hide(
+    mut query: Query<&mut Visibility, With<Ball>>,
+) {
+    println!("balls: hide");
+    for mut visibility in query.iter_mut() { *visibility = Visibility::Hidden }
+}
+
+fn show(
+    mut query: Query<&mut Visibility, With<Ball>>,
+) {
+    for mut visibility in query.iter_mut() { *visibility = Visibility::Inherited }
+}
+
 fn despawn(
     mut commands: Commands,
     query: Query<Entity, With<Ball>>,
 ) {
     println!("balls: despawn all");
-    for entity in query.iter() { commands.entity(entity).despawn() }
-}
-
-fn reset_ball_count(
-    mut ball_count: ResMut<BallCount>,
-) {
-    println!("balls: reset ballCount");
-    **ball_count = BALL_COUNT;
+    for entity in query.iter() { commands.entity(entity).despawn_recursive() }
 }
 
 pub struct BallsPlugin;
@@ -212,57 +575,46 @@ pub struct BallsPlugin;
 impl Plugin for BallsPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_event::<DespawnEvent>()
-            .add_systems(OnEnter(AppState::Ingame), setup)
-            .add_systems(Update, (
+            .add_event::<BallPopped>()
+            .observe(restart)
+            .add_systems(OnEnter(AppState::Ingame), setup_sound.run_if(resource_exists::<AssetServer>))
+            .add_systems(FixedUpdate, (
                 apply_velocity,
-                check_wall_collisions,
                 check_ball_collisions,
-                mouse_click,
-                play_despawn_sound,
-            ).run_if(in_state(AppState::Ingame)))
-            .add_systems(OnEnter(AppState::Gameover), despawn)
-            .add_systems(OnExit(AppState::Gameover), reset_ball_count)
-            .add_systems(OnEnter(AppState::Gameclear), despawn)
-            .add_systems(OnExit(AppState::Gameclear), reset_ball_count)
+                check_wall_collisions,
+            ).chain().in_set(PhysicsSet).run_if(in_state(Round::Playing)))
+            .add_systems(Update, (
+                interpolate.before(ClickSet::Pick),
+                pop_clicked.in_set(ClickSet::Handle),
+            ).run_if(in_state(Round::Playing)))
+            .add_systems(Update, (
+                add_mesh,
+                add_label,
+                update_label,
+                update_material,
+                highlight_next.run_if(resource_exists::<NextNumber>),
+                play_despawn_sound.run_if(in_state(Round::Playing)),
+            ).run_if(resource_exists::<AssetServer>))
+            .add_systems(OnEnter(Round::Paused), hide)
+            .add_systems(OnEnter(Round::Playing), show)
+            .add_systems(OnExit(AppState::Ingame), despawn)
         ;
     }
 }
 
-fn random_color() -> Color {
-    let mut rng = rand::thread_rng();
+/// Color of a ball when it spawns or bounces, from the palette when the
+/// color decides what may be popped.
+pub(super) fn ball_color(rng: &mut impl Rng, mode: GameMode) -> Color {
+    if mode != GameMode::ColorMatch { return random_color(rng) }
+    PALETTE[rng.gen_range(0..PALETTE.len())].0
+}
+
+fn random_color(rng: &mut impl Rng) -> Color {
     let die_color = Uniform::from(0.0..1.0);
 
     Color::srgb(
-        die_color.sample(&mut rng),
-        die_color.sample(&mut rng),
-        die_color.sample(&mut rng)
+        die_color.sample(rng),
+        die_color.sample(rng),
+        die_color.sample(rng)
     )
 }
-
-fn set_ball_positions() -> Vec<Vec3> {
-    vec![
-        Vec3::new(SIZE.x *  0.0, SIZE.y *  0.0,  0.0),
-        Vec3::new(SIZE.x *  0.0, SIZE.y *  1.0,  1.0),
-        Vec3::new(SIZE.x *  1.0, SIZE.y *  1.0,  2.0),
-        Vec3::new(SIZE.x *  1.0, SIZE.y *  0.0,  3.0),
-        Vec3::new(SIZE.x *  1.0, SIZE.y * -1.0,  4.0),
-        Vec3::new(SIZE.x *  0.0, SIZE.y * -1.0,  5.0),
-        Vec3::new(SIZE.x * -1.0, SIZE.y * -1.0,  6.0),
-        Vec3::new(SIZE.x * -1.0, SIZE.y *  0.0,  7.0),
-        Vec3::new(SIZE.x * -1.0, SIZE.y *  1.0,  8.0),
-        Vec3::new(SIZE.x * -1.0, SIZE.y *  2.0,  8.0),
-        Vec3::new(SIZE.x *  0.0, SIZE.y *  2.0,  9.0),
-        Vec3::new(SIZE.x *  1.0, SIZE.y *  2.0, 10.0),
-        Vec3::new(SIZE.x *  2.0, SIZE.y *  2.0, 11.0),
-        Vec3::new(SIZE.x *  2.0, SIZE.y *  1.0, 12.0),
-        Vec3::new(SIZE.x *  2.0, SIZE.y *  0.0, 13.0),
-        Vec3::new(SIZE.x *  2.0, SIZE.y * -1.0, 14.0),
-        Vec3::new(SIZE.x *  2.0, SIZE.y * -2.0, 15.0),
-        Vec3::new(SIZE.x *  1.0, SIZE.y * -2.0, 16.0),
-        Vec3::new(SIZE.x *  0.0, SIZE.y * -2.0, 17.0),
-        Vec3::new(SIZE.x * -1.0, SIZE.y * -2.0, 18.0),
-        Vec3::new(SIZE.x * -2.0, SIZE.y * -2.0, 19.0),
-        Vec3::new(SIZE.x * -2.0, SIZE.y * -1.0, 20.0),
-    ]
-}
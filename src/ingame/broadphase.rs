@@ -0,0 +1,143 @@
+use bevy::{
+    prelude::*,
+    utils::HashMap,
+};
+
+// neighbour cells visited from each cell, so every pair of cells is visited once
+const NEIGHBOURS: [IVec2; 4] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 1),
+    IVec2::new(0, 1),
+    IVec2::new(1, 1),
+];
+
+/// A ball as seen by collision detection.
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+    pub position: Vec2,
+    /// Diameter in pixels.
+    pub size: f32,
+}
+
+fn touching(body1: &Body, body2: &Body) -> bool {
+    let contact = (body1.size + body2.size) / 2.0;
+    body1.position.distance_squared(body2.position) <= contact.powi(2)
+}
+
+/// Every touching pair `(i, j)` with `i < j`, testing all pairs.
+#[cfg(test)]
+pub fn brute_force_contacts(bodies: &[Body]) -> Vec<(usize, usize)> {
+    let mut contacts = Vec::new();
+
+    for i in 0..bodies.len() {
+        for j in i + 1..bodies.len() {
+            if touching(&bodies[i], &bodies[j]) { contacts.push((i, j)) }
+        }
+    }
+
+    contacts
+}
+
+/// Every touching pair `(i, j)` with `i < j`, testing only bodies in
+/// neighbouring cells of a uniform grid one body wide.
+///
+/// Pairs come out in the same order as `brute_force_contacts`.
+pub fn grid_contacts(bodies: &[Body]) -> Vec<(usize, usize)> {
+    let cell_size = bodies.iter().map(|body| body.size).fold(1.0, f32::max);
+    let cell = |position: Vec2| (position / cell_size).floor().as_ivec2();
+
+    let mut grid: HashMap<IVec2, Vec<usize>> = HashMap::new();
+    for (i, body) in bodies.iter().enumerate() {
+        grid.entry(cell(body.position)).or_default().push(i);
+    }
+
+    let mut contacts = Vec::new();
+    let mut check = |i: usize, j: usize| {
+        if touching(&bodies[i], &bodies[j]) { contacts.push((i.min(j), i.max(j))) }
+    };
+
+    for (&key, indices) in grid.iter() {
+        for (n, &i) in indices.iter().enumerate() {
+            for &j in &indices[n + 1..] { check(i, j) }
+        }
+
+        for offset in NEIGHBOURS {
+            let Some(neighbours) = grid.get(&(key + offset)) else { continue };
+            for &i in indices {
+                for &j in neighbours { check(i, j) }
+            }
+        }
+    }
+
+    contacts.sort_unstable();
+    contacts
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use rand::{
+        distributions::{Distribution, Uniform},
+        rngs::StdRng,
+        SeedableRng,
+    };
+
+    use super::*;
+    use crate::WINDOW_SIZE;
+
+    fn random_bodies(count: usize, seed: u64) -> Vec<Body> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let die_x = Uniform::new_inclusive(-WINDOW_SIZE.x / 2.0, WINDOW_SIZE.x / 2.0);
+        let die_y = Uniform::new_inclusive(-WINDOW_SIZE.y / 2.0, WINDOW_SIZE.y / 2.0);
+        let die_size = Uniform::new_inclusive(10.0, 40.0);
+
+        (0..count)
+            .map(|_| Body {
+                position: Vec2::new(die_x.sample(&mut rng), die_y.sample(&mut rng)),
+                size: die_size.sample(&mut rng),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grid_finds_the_same_contacts() {
+        for seed in 0..50 {
+            for count in [0, 1, 2, 20, 300] {
+                let bodies = random_bodies(count, seed);
+                assert_eq!(grid_contacts(&bodies), brute_force_contacts(&bodies), "seed {} count {}", seed, count);
+            }
+        }
+    }
+
+    #[test]
+    fn contacts_across_cell_borders_are_found() {
+        let bodies = [
+            Body { position: Vec2::new(-0.5, -0.5), size: 30.0 },
+            Body { position: Vec2::new(0.5, 0.5), size: 30.0 },
+            Body { position: Vec2::new(-29.0, 29.0), size: 30.0 },
+            Body { position: Vec2::new(60.0, 0.0), size: 30.0 },
+        ];
+
+        assert_eq!(grid_contacts(&bodies), vec![(0, 1)]);
+    }
+
+    /// `cargo test --release -- --ignored --nocapture bench_contacts`
+    #[test]
+    #[ignore]
+    fn bench_contacts() {
+        for (count, runs) in [(20, 10_000), (500, 100), (5_000, 3)] {
+            let bodies = random_bodies(count, 0);
+
+            let start = Instant::now();
+            for _ in 0..runs { std::hint::black_box(brute_force_contacts(&bodies)); }
+            let brute_force = start.elapsed() / runs;
+
+            let start = Instant::now();
+            for _ in 0..runs { std::hint::black_box(grid_contacts(&bodies)); }
+            let grid = start.elapsed() / runs;
+
+            println!("{:>5} balls: brute force {:>12?}, grid {:>12?}", count, brute_force, grid);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+
+/// A ball as seen by the collision response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub mass: f32,
+    pub restitution: f32,
+    /// Diameter in pixels.
+    pub size: f32,
+}
+
+/// Pushes two touching particles apart along the contact normal and, when
+/// they approach each other, applies an impulse using the lower restitution.
+///
+/// Returns whether an impulse was applied.
+pub fn resolve(particle1: &mut Particle, particle2: &mut Particle) -> bool {
+    let offset = particle2.position - particle1.position;
+    let distance = offset.length();
+    let contact = (particle1.size + particle2.size) / 2.0;
+
+    if distance > contact { return false }
+
+    // two balls on the same spot are pushed apart sideways
+    let normal = if distance > 0.0 { offset / distance } else { Vec2::X };
+    let inverse_mass1 = 1.0 / particle1.mass;
+    let inverse_mass2 = 1.0 / particle2.mass;
+    let inverse_mass = inverse_mass1 + inverse_mass2;
+
+    let overlap = contact - distance;
+    particle1.position -= normal * overlap * inverse_mass1 / inverse_mass;
+    particle2.position += normal * overlap * inverse_mass2 / inverse_mass;
+
+    let approach = (particle2.velocity - particle1.velocity).dot(normal);
+    if approach >= 0.0 { return false }
+
+    let restitution = particle1.restitution.min(particle2.restitution);
+    let impulse = -(1.0 + restitution) * approach / inverse_mass;
+    particle1.velocity -= normal * impulse * inverse_mass1;
+    particle2.velocity += normal * impulse * inverse_mass2;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{
+        distributions::{Distribution, Uniform},
+        rngs::StdRng,
+        Rng,
+        SeedableRng,
+    };
+
+    use super::*;
+
+    const CASES: u64 = 1_000;
+
+    fn momentum(particles: [&Particle; 2]) -> Vec2 {
+        particles.iter().map(|particle| particle.velocity * particle.mass).sum()
+    }
+
+    fn energy(particles: [&Particle; 2]) -> f32 {
+        particles.iter().map(|particle| 0.5 * particle.mass * particle.velocity.length_squared()).sum()
+    }
+
+    /// Two overlapping particles with random speed, mass and size.
+    fn random_pair(rng: &mut impl Rng, restitution: f32) -> (Particle, Particle) {
+        let die_velocity = Uniform::new_inclusive(-300.0, 300.0);
+        let die_mass = Uniform::new_inclusive(0.1, 10.0);
+        let die_size = Uniform::new_inclusive(10.0, 40.0);
+        let mut particle = || Particle {
+            position: Vec2::ZERO,
+            velocity: Vec2::new(die_velocity.sample(rng), die_velocity.sample(rng)),
+            mass: die_mass.sample(rng),
+            restitution,
+            size: die_size.sample(rng),
+        };
+        let particle1 = particle();
+        let mut particle2 = particle();
+        let contact = (particle1.size + particle2.size) / 2.0;
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        particle2.position = Vec2::from_angle(angle) * rng.gen_range(0.0..contact);
+
+        (particle1, particle2)
+    }
+
+    fn assert_close(a: f32, b: f32, message: &str) {
+        assert!((a - b).abs() <= 1e-3 * a.abs().max(b.abs()).max(1.0), "{}: {} != {}", message, a, b);
+    }
+
+    #[test]
+    fn elastic_collisions_conserve_momentum_and_energy() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..CASES {
+            let (mut particle1, mut particle2) = random_pair(&mut rng, 1.0);
+            let momentum_before = momentum([&particle1, &particle2]);
+            let energy_before = energy([&particle1, &particle2]);
+
+            resolve(&mut particle1, &mut particle2);
+
+            let momentum_after = momentum([&particle1, &particle2]);
+            assert_close(momentum_after.x, momentum_before.x, "momentum x");
+            assert_close(momentum_after.y, momentum_before.y, "momentum y");
+            assert_close(energy([&particle1, &particle2]), energy_before, "energy");
+        }
+    }
+
+    #[test]
+    fn inelastic_collisions_conserve_momentum_and_lose_energy() {
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..CASES {
+            let restitution = rng.gen_range(0.0..1.0);
+            let (mut particle1, mut particle2) = random_pair(&mut rng, restitution);
+            let momentum_before = momentum([&particle1, &particle2]);
+            let energy_before = energy([&particle1, &particle2]);
+
+            resolve(&mut particle1, &mut particle2);
+
+            let momentum_after = momentum([&particle1, &particle2]);
+            assert_close(momentum_after.x, momentum_before.x, "momentum x");
+            assert_close(momentum_after.y, momentum_before.y, "momentum y");
+            assert!(energy([&particle1, &particle2]) <= energy_before * (1.0 + 1e-4));
+        }
+    }
+
+    #[test]
+    fn resolved_particles_separate() {
+        let mut rng = StdRng::seed_from_u64(2);
+
+        for _ in 0..CASES {
+            let (mut particle1, mut particle2) = random_pair(&mut rng, 1.0);
+
+            resolve(&mut particle1, &mut particle2);
+
+            let contact = (particle1.size + particle2.size) / 2.0;
+            let normal = (particle2.position - particle1.position).normalize();
+            assert_close(particle1.position.distance(particle2.position), contact, "distance");
+            assert!((particle2.velocity - particle1.velocity).dot(normal) >= -1e-3);
+        }
+    }
+
+    #[test]
+    fn balls_on_the_same_spot_are_pushed_apart() {
+        let particle = Particle {
+            position: Vec2::ZERO,
+            velocity: Vec2::ZERO,
+            mass: 1.0,
+            restitution: 1.0,
+            size: 30.0,
+        };
+        let (mut particle1, mut particle2) = (particle, particle);
+
+        resolve(&mut particle1, &mut particle2);
+
+        assert_eq!(particle1.position, Vec2::new(-15.0, 0.0));
+        assert_eq!(particle2.position, Vec2::new(15.0, 0.0));
+    }
+
+    #[test]
+    fn separating_balls_keep_their_velocity() {
+        let mut particle1 = Particle {
+            position: Vec2::ZERO,
+            velocity: Vec2::new(-10.0, 0.0),
+            mass: 1.0,
+            restitution: 1.0,
+            size: 30.0,
+        };
+        let mut particle2 = Particle { position: Vec2::new(20.0, 0.0), velocity: Vec2::new(10.0, 0.0), ..particle1 };
+
+        assert!(!resolve(&mut particle1, &mut particle2));
+        assert_eq!(particle1.velocity, Vec2::new(-10.0, 0.0));
+        assert_eq!(particle2.velocity, Vec2::new(10.0, 0.0));
+    }
+}
@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::{
+    PhysicsSet,
+    RestartRound,
+    balls::{Ball, BallColor, BallPopped, PALETTE},
+};
+use crate::{
+    Round,
+    GameMode,
+    GameRng,
+    level::BallKind,
+};
+
+/// Seconds a click on a ball of the wrong color takes off the time limit.
+pub const WRONG_COLOR_PENALTY: f32 = 2.0;
+
+/// Palette color the balls must have to be popped in a color-match round.
+///
+/// Only exists during color-match rounds.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetColor(usize);
+
+impl TargetColor {
+    pub fn color(self) -> Color {
+        PALETTE[self.0].0
+    }
+
+    pub fn name(self) -> &'static str {
+        PALETTE[self.0].1
+    }
+
+    /// Whether a click may pop a ball; special balls keep their own color
+    /// and can always be popped.
+    pub fn matches(self, kind: BallKind, color: Color) -> bool {
+        kind != BallKind::Normal || color == self.color()
+    }
+}
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut commands: Commands,
+    mode: Res<GameMode>,
+) {
+    if *mode != GameMode::ColorMatch {
+        commands.remove_resource::<TargetColor>();
+        return
+    }
+    // replaced by a color on the field in the first step
+    commands.insert_resource(TargetColor(0));
+}
+
+/// Picks a new target after every pop, and whenever no ball has the target
+/// color any more.
+///
+/// Draws from `GameRng` by fixed steps after the physics, so a replay picks
+/// the same targets at any frame rate.
+fn retarget(
+    mut target: ResMut<TargetColor>,
+    mut rng: ResMut<GameRng>,
+    mut popped_events: EventReader<BallPopped>,
+    query: Query<(&BallColor, &BallKind), With<Ball>>,
+) {
+    let popped = popped_events.read().count() > 0;
+    let mut colors: Vec<usize> = query.iter()
+        .filter(|(_, kind)| **kind == BallKind::Normal)
+        .filter_map(|(color, _)| PALETTE.iter().position(|(palette_color, _)| *palette_color == **color))
+        .collect();
+    colors.sort_unstable();
+    colors.dedup();
+
+    if colors.is_empty() || (!popped && colors.contains(&target.0)) { return }
+
+    target.0 = colors[rng.gen_range(0..colors.len())];
+    println!("colormatch: target {}", target.name());
+}
+
+pub struct ColorMatchPlugin;
+
+impl Plugin for ColorMatchPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .observe(reset)
+            .add_systems(FixedUpdate, retarget
+                .after(PhysicsSet)
+                .run_if(in_state(Round::Playing))
+                .run_if(resource_exists::<TargetColor>))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_normal_balls_must_match() {
+        let target = TargetColor(1);
+        let other = PALETTE[2].0;
+
+        assert!(target.matches(BallKind::Normal, target.color()));
+        assert!(!target.matches(BallKind::Normal, other));
+        assert!(target.matches(BallKind::Tough(2), other));
+    }
+}
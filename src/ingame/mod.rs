@@ -1,18 +1,131 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    window::WindowFocused,
+};
+
+use crate::{
+    AppState,
+    ClickInput,
+    GameMode,
+    Round,
+    Seed,
+    actions::ActionMap,
+    difficulty::Difficulty,
+    level::CurrentLevel,
+    playarea::PlayArea,
+};
 
 mod balls;
+mod broadphase;
+mod collision;
+mod colormatch;
 mod pausebutton;
+mod pausemenu;
+mod picking;
+mod recorder;
+mod reticle;
+mod score;
 mod scoreboard;
+mod sequence;
+mod spawner;
+mod splits;
 mod timer;
+mod versus;
+#[cfg(test)]
+mod tests;
+
+pub use recorder::{Playback, Recording};
+pub use splits::Splits;
+pub use versus::Tallies;
+
+/// Order of click handling: clicks are gathered from the input or a replay,
+/// picked into events, then handled.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+enum ClickSet {
+    Input,
+    Pick,
+    Handle,
+}
+
+/// Physics of the balls, stepped in `FixedUpdate`.
+///
+/// Everything else that changes the round outside of clicks, and draws from
+/// `GameRng`, steps after it, so a round plays out the same at any frame rate.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+struct PhysicsSet;
+
+/// Starts the round afresh, on entering `AppState::Ingame` and on a retry
+/// from the pause menu.
+///
+/// Plugins with state of their own in a round observe it to reset that
+/// state, so the screens starting a round never need to know about them.
+#[derive(Event, Debug)]
+pub struct RestartRound;
+
+/// Triggers `RestartRound` with the seed of the new round in place.
+fn restart_round(commands: &mut Commands) {
+    commands.add(|world: &mut World| {
+        let mut seed = world.resource_mut::<Seed>();
+        *seed = seed.next();
+    });
+    commands.trigger(RestartRound);
+}
+
+fn start_round(
+    mut commands: Commands,
+) {
+    restart_round(&mut commands);
+}
+
+/// Whether a step of this frame already ended the round, with its results
+/// screen entered only in the next frame.
+fn round_ending(next_state: Res<NextState<AppState>>) -> bool {
+    matches!(*next_state, NextState::Pending(_))
+}
+
+fn clear_clicks(
+    mut clicks: ResMut<ClickInput>,
+) {
+    clicks.clear();
+}
 
+/// Gameplay of a round: balls, timer, scoreboard, pause button, pause menu,
+/// the spawner of the endless mode, the splits of the time attack, the
+/// target color of the color match, the order of the number sequence, the
+/// tallies of the two players and the recording and playback of replays.
+///
+/// Systems that load assets, render or play sounds only run when an
+/// `AssetServer` exists, so the plugin also runs headless under
+/// `MinimalPlugins` with clicks pushed into `ClickInput`.
 pub struct IngamePlugin;
 
 impl Plugin for IngamePlugin {
     fn build(&self, app: &mut App) {
         app
+            .add_sub_state::<Round>()
+            .init_resource::<ClickInput>()
+            .init_resource::<ActionMap>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<Difficulty>()
+            .init_resource::<GameMode>()
+            .init_resource::<PlayArea>()
+            .add_event::<WindowFocused>()
+            .configure_sets(Update, (ClickSet::Input, ClickSet::Pick, ClickSet::Handle).chain())
+            .add_systems(OnEnter(AppState::Ingame), start_round)
+            .add_systems(Last, clear_clicks)
             .add_plugins(balls::BallsPlugin)
+            .add_plugins(colormatch::ColorMatchPlugin)
             .add_plugins(pausebutton::PausebuttonPlugin)
+            .add_plugins(pausemenu::PausemenuPlugin)
+            .add_plugins(picking::PickingPlugin)
+            .add_plugins(recorder::RecorderPlugin)
+            .add_plugins(reticle::ReticlePlugin)
+            .add_plugins(score::ScorePlugin)
             .add_plugins(scoreboard::ScoreboardPlugin)
-            .add_plugins(timer::TimerPlugin);
+            .add_plugins(sequence::SequencePlugin)
+            .add_plugins(spawner::SpawnerPlugin)
+            .add_plugins(splits::SplitsPlugin)
+            .add_plugins(timer::TimerPlugin)
+            .add_plugins(versus::VersusPlugin);
     }
 }
@@ -1,14 +1,16 @@
-use bevy::{
-    prelude::*,
-    window::PrimaryWindow,
-};
+use bevy::prelude::*;
 
+use super::{
+    ClickSet,
+    RestartRound,
+    picking::{Pickable, UiClicked},
+};
 use crate::{
-    WINDOW_SIZE,
-    CURSOR_RANGE,
     PATH_IMAGE_PAUSEBUTTON,
     AppState,
-    Config,
+    Round,
+    actions::{Action, ActionInput},
+    playarea::PlayArea,
 };
 
 const IMAGE_SIZE: u32 = 64;
@@ -25,19 +27,13 @@ fn setup(
     mut commands: Commands,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     asset_server: Res<AssetServer>,
-    config: Res<Config>,
+    play_area: Res<PlayArea>,
 ) {
-    if !config.setup_ingame { return };
-
     println!("pausebutton: setup");
     let layout = TextureAtlasLayout::from_grid(UVec2::splat(IMAGE_SIZE), 2, 1, None, None);
     let texture_atlas_layout = texture_atlas_layouts.add(layout);
     let animation_indices = Pausebutton { first: 0, last: 1 };
-    let pos = Vec3::new(
-        WINDOW_SIZE.x / 2.0 - SIZE / 2.0 - PADDING,
-        -WINDOW_SIZE.y / 2.0 + SIZE / 2.0 + PADDING,
-        99.0
-    );
+    let pos = corner(&play_area).extend(99.0);
 
     commands.spawn((
         SpriteBundle {
@@ -54,45 +50,62 @@ fn setup(
             index: animation_indices.first,
         },
         animation_indices,
+        Pickable::ui(SIZE / 2.0),
     ))
     .insert(Name::new("pausebutton"));
 }
 
-fn update(
-    mut query: Query<(&Transform, &Pausebutton, &mut TextureAtlas), With<Pausebutton>>,
-    mut config: ResMut<Config>,
-    mut next_state: ResMut<NextState<AppState>>,
-    mouse_events: Res<ButtonInput<MouseButton>>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
+/// Bottom right corner of the play area, where the button sits.
+fn corner(play_area: &PlayArea) -> Vec2 {
+    let half_size = play_area.half_size();
+    Vec2::new(half_size.x - SIZE / 2.0 - PADDING, -half_size.y + SIZE / 2.0 + PADDING)
+}
+
+fn anchor(
+    mut query: Query<&mut Transform, With<Pausebutton>>,
+    play_area: Res<PlayArea>,
+) {
+    for mut transform in query.iter_mut() {
+        transform.translation = corner(&play_area).extend(transform.translation.z);
+    }
+}
+
+/// Back to the pause icon in its corner, also on a retry.
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut query: Query<(&Pausebutton, &mut TextureAtlas, &mut Transform)>,
+    play_area: Res<PlayArea>,
 ) {
-    if !mouse_events.just_pressed(MouseButton::Left) { return }
+    for (prop, mut atlas, mut transform) in query.iter_mut() {
+        atlas.index = prop.first;
+        transform.translation = corner(&play_area).extend(transform.translation.z);
+    }
+}
 
-    let window = window_query.single();
-    let mut cursor_pos = window.cursor_position().unwrap();
-    let Ok((transform, prop, mut atlas)) = query.get_single_mut() else { return };
-    let pausebutton_pos = transform.translation.truncate();
-    cursor_pos = Vec2::new(
-        cursor_pos.x - WINDOW_SIZE.x / 2.0,
-        -cursor_pos.y + WINDOW_SIZE.y / 2.0
-    );
+fn update(
+    mut next_round: ResMut<NextState<Round>>,
+    mut clicked_events: EventReader<UiClicked>,
+    query: Query<Entity, With<Pausebutton>>,
+    round: Res<State<Round>>,
+    actions: ActionInput,
+) {
+    let clicked = clicked_events.read().any(|clicked| query.contains(clicked.entity));
+    if !clicked && !actions.just_pressed(Action::Pause) { return }
 
-    let distance = cursor_pos.distance(pausebutton_pos);
+    let next = if *round.get() == Round::Playing { Round::Paused } else { Round::Playing };
+    println!("pausebutton: moved round to {:?} from {:?}", next, round.get());
+    next_round.set(next);
+}
 
-    if distance < SIZE - CURSOR_RANGE {
-        if atlas.index == prop.first {
-            println!("pausebutton: toggled");
-            atlas.index = prop.last;
-            println!("pausebutton: moved state to Pause from Ingame");
-            next_state.set(AppState::Pause);
-        } else {
-            if config.setup_ingame {
-                println!("pausebutton: change config.setup_ingame to false");
-                config.setup_ingame = false;
-            }
+fn update_icon(
+    mut query: Query<(&Pausebutton, &mut TextureAtlas)>,
+    round: Res<State<Round>>,
+) {
+    for (prop, mut atlas) in query.iter_mut() {
+        let index = if *round.get() == Round::Paused { prop.last } else { prop.first };
+        if atlas.index != index {
             println!("pausebutton: toggled");
-            atlas.index = prop.first;
-            println!("pausebutton: moved state to Ingame from Pause");
-            next_state.set(AppState::Ingame);
+            atlas.index = index;
         }
     }
 }
@@ -110,11 +123,16 @@ pub struct PausebuttonPlugin;
 impl Plugin for PausebuttonPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(OnEnter(AppState::Ingame), setup)
-            .add_systems(Update, update.run_if(in_state(AppState::Ingame)))
-            .add_systems(Update, update.run_if(in_state(AppState::Pause)))
-            .add_systems(OnEnter(AppState::Gameover), despawn)
-            .add_systems(OnEnter(AppState::Gameclear), despawn)
+            .add_systems(OnEnter(AppState::Ingame), setup.run_if(resource_exists::<AssetServer>))
+            .observe(reset)
+            .add_systems(Update, update
+                .in_set(ClickSet::Handle)
+                .run_if(in_state(Round::Playing).or_else(in_state(Round::Paused))))
+            .add_systems(Update, anchor
+                .before(ClickSet::Pick)
+                .run_if(resource_changed::<PlayArea>))
+            .add_systems(Update, update_icon.run_if(state_changed::<Round>))
+            .add_systems(OnExit(AppState::Ingame), despawn)
         ;
     }
 }
@@ -0,0 +1,167 @@
+use bevy::{
+    prelude::*,
+    window::WindowFocused,
+};
+
+use super::restart_round;
+use crate::{
+    PATH_FONT,
+    AppState,
+    Round,
+    actions::{Action, ActionInput, ActionMap},
+};
+
+const PAUSE_TEXT: &str = "ポーズ";
+const PAUSE_SIZE: f32 = 32.0;
+const RESUME_TEXT: &str = "つづける";
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const TEXT_SIZE: f32 = 20.0;
+const TEXT_PADDING: f32 = 10.0;
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const OVERLAY_COLOR: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+#[derive(Component)]
+struct Pausemenu;
+
+/// Entries of the pause menu, tappable on touch screens.
+#[derive(Component, Debug, Clone, Copy)]
+enum PausemenuButton {
+    Resume,
+    Retry,
+    BackToTitle,
+    Settings,
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    action_map: Res<ActionMap>,
+) {
+    println!("pausemenu: setup");
+    let text_style = |font_size| TextStyle {
+        font: asset_server.load(PATH_FONT),
+        font_size,
+        color: TEXT_COLOR,
+    };
+    let entries = [
+        (PausemenuButton::Resume, format!("{}: {}", RESUME_TEXT, action_map.hint(Action::Pause))),
+        (PausemenuButton::Retry, format!("{}: {}", Action::Retry.label(), action_map.hint(Action::Retry))),
+        (PausemenuButton::BackToTitle, format!("{}: {}", Action::BackToTitle.label(), action_map.hint(Action::BackToTitle))),
+        (PausemenuButton::Settings, format!("{}: {}", Action::Settings.label(), action_map.hint(Action::Settings))),
+    ];
+    // overlay dimming the frozen round
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(TEXT_PADDING),
+                ..Default::default()
+            },
+            background_color: OVERLAY_COLOR.into(),
+            z_index: ZIndex::Global(1),
+            ..Default::default()
+        },
+        Pausemenu,
+    ))
+    .insert(Name::new("pausemenu"))
+    .with_children(|parent| {
+        // pause
+        parent.spawn(
+            TextBundle::from_section(PAUSE_TEXT, text_style(PAUSE_SIZE))
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(TEXT_PADDING * 2.0)),
+                    ..Default::default()
+                })
+        )
+        .insert(Name::new("pause"));
+        // entries
+        for (button, text) in entries {
+            parent.spawn((
+                TextBundle::from_section(text, text_style(TEXT_SIZE))
+                    .with_background_color(BUTTON_COLOR),
+                Interaction::default(),
+                button,
+            ))
+            .insert(Name::new("entry"));
+        }
+    });
+}
+
+fn update(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut next_round: ResMut<NextState<Round>>,
+    button_query: Query<(&Interaction, &PausemenuButton), Changed<Interaction>>,
+    actions: ActionInput,
+) {
+    let pressed = button_query.iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| *button);
+    // gamepad Start resumes here, so Retry is only taken from the keyboard and mouse
+    let action_buttons = [
+        (actions.pointer_just_pressed(Action::Retry), PausemenuButton::Retry),
+        (actions.just_pressed(Action::BackToTitle), PausemenuButton::BackToTitle),
+        (actions.just_pressed(Action::Settings), PausemenuButton::Settings),
+    ];
+    let action_pressed = action_buttons.into_iter()
+        .filter(|(just_pressed, _)| *just_pressed)
+        .map(|(_, button)| button);
+
+    for button in action_pressed.chain(pressed) {
+        match button {
+            PausemenuButton::Resume => {
+                println!("pausemenu: moved round to Playing from Paused");
+                next_round.set(Round::Playing);
+            },
+            PausemenuButton::Retry => {
+                println!("pausemenu: restarted round");
+                restart_round(&mut commands);
+                next_round.set(Round::Playing);
+            },
+            PausemenuButton::BackToTitle => {
+                println!("pausemenu: moved state to Mainmenu from Ingame");
+                next_state.set(AppState::Mainmenu);
+            },
+            PausemenuButton::Settings => {
+                println!("pausemenu: moved round to Settings from Paused");
+                next_round.set(Round::Settings);
+            },
+        }
+    }
+}
+
+/// Pauses the round when the window loses focus.
+fn pause_on_focus_lost(
+    mut next_round: ResMut<NextState<Round>>,
+    mut focused_events: EventReader<WindowFocused>,
+) {
+    if focused_events.read().all(|event| event.focused) { return }
+
+    println!("pausemenu: window lost focus");
+    next_round.set(Round::Paused);
+}
+
+fn despawn(
+    mut commands: Commands,
+    query: Query<Entity, With<Pausemenu>>,
+) {
+    println!("pausemenu: despawn");
+    for entity in query.iter() { commands.entity(entity).despawn_recursive() }
+}
+
+pub struct PausemenuPlugin;
+
+impl Plugin for PausemenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(Round::Paused), setup.run_if(resource_exists::<AssetServer>))
+            .add_systems(Update, update.run_if(in_state(Round::Paused)))
+            .add_systems(Update, pause_on_focus_lost.run_if(in_state(Round::Playing)))
+            .add_systems(OnExit(Round::Paused), despawn)
+        ;
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::{
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use super::{ClickSet, balls::Position, round_ending};
+use crate::{
+    AppState,
+    Round,
+    Click,
+    ClickInput,
+    Player,
+    actions::{Action, ActionInput},
+    difficulty::Difficulty,
+};
+
+/// Something clicks can hit: within `radius` times its scale, plus the
+/// cursor range of the difficulty, of its translation, or of its `Position`
+/// for a ball.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Pickable {
+    pub layer: PickLayer,
+    pub radius: f32,
+}
+
+/// UI elements are picked before balls, and a click on one never reaches the balls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickLayer {
+    Ui,
+    Ball,
+}
+
+impl Pickable {
+    pub fn ui(radius: f32) -> Self {
+        Self { layer: PickLayer::Ui, radius }
+    }
+
+    /// A ball, a unit circle scaled to its size.
+    pub fn ball() -> Self {
+        Self { layer: PickLayer::Ball, radius: 0.5 }
+    }
+}
+
+/// A click on a ball, one event per ball under the cursor.
+#[derive(Event, Debug)]
+pub struct BallClicked {
+    pub entity: Entity,
+    pub player: Player,
+}
+
+/// A click on a UI element such as the pause button.
+#[derive(Event, Debug)]
+pub struct UiClicked {
+    pub entity: Entity,
+}
+
+/// A click during the round that hit no ball.
+#[derive(Event, Debug)]
+pub struct ClickMissed;
+
+/// A click during the round that went past the UI to the balls, hit or missed.
+#[derive(Event, Debug)]
+pub struct FieldClicked(pub Click);
+
+/// Turns pointer presses of `Action::Pop` and new touches into world positions through the
+/// camera, so zoom, shake and letterboxing are all accounted for.
+///
+/// Reads nothing without a window, so it also runs headless.
+fn pointer_clicks(
+    mut clicks: ResMut<ClickInput>,
+    actions: ActionInput,
+    touches: Option<Res<Touches>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let Ok((camera, camera_transform)) = camera_query.get_single() else { return };
+    // the cursor may be outside the window
+    let cursor_pos = window.cursor_position()
+        .filter(|_| actions.pointer_just_pressed(Action::Pop));
+    // every finger down this frame is a click of its own
+    let touch_positions = touches.iter().flat_map(|touches| touches.iter_just_pressed()).map(|touch| touch.position());
+    // positions are relative to the window, the camera wants them relative to its letterboxed viewport
+    let viewport_origin = camera.logical_viewport_rect().map_or(Vec2::ZERO, |rect| rect.min);
+
+    for screen_pos in cursor_pos.into_iter().chain(touch_positions) {
+        let Some(world_pos) = camera.viewport_to_world_2d(camera_transform, screen_pos - viewport_origin) else { continue };
+        clicks.push(world_pos.into());
+    }
+}
+
+fn pick(
+    mut ball_events: EventWriter<BallClicked>,
+    mut ui_events: EventWriter<UiClicked>,
+    (mut missed_events, mut field_events): (EventWriter<ClickMissed>, EventWriter<FieldClicked>),
+    clicks: Res<ClickInput>,
+    (round, next_state): (Res<State<Round>>, Res<NextState<AppState>>),
+    query: Query<(Entity, &Transform, &Pickable, Option<&Position>)>,
+    difficulty: Res<Difficulty>,
+) {
+    // a round ended by this frame's physics steps takes no more clicks
+    let playing = *round.get() == Round::Playing && !round_ending(next_state);
+    let cursor_range = difficulty.cursor_range();
+    // balls are hit where the latest physics step left them, not where they are drawn, so
+    // a replay hits the same balls at any frame rate
+    let hits = |cursor_pos: Vec2, layer: PickLayer| {
+        query.iter().filter(move |(_, transform, pickable, position)| {
+            let center = position.map_or(transform.translation.truncate(), |position| **position);
+            pickable.layer == layer
+            && cursor_pos.distance(center) < pickable.radius * transform.scale.x + cursor_range
+        })
+    };
+
+    for click in clicks.iter() {
+        if let Some((entity, ..)) = hits(click.pos, PickLayer::Ui).next() {
+            ui_events.send(UiClicked { entity });
+            continue
+        }
+        // paused balls can not be popped
+        if !playing { continue }
+        field_events.send(FieldClicked(*click));
+
+        let mut hit = false;
+        for (entity, ..) in hits(click.pos, PickLayer::Ball) {
+            ball_events.send(BallClicked { entity, player: click.player });
+            hit = true;
+        }
+
+        if !hit { missed_events.send(ClickMissed); }
+    }
+}
+
+pub struct PickingPlugin;
+
+impl Plugin for PickingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<BallClicked>()
+            .add_event::<UiClicked>()
+            .add_event::<ClickMissed>()
+            .add_event::<FieldClicked>()
+            .add_systems(PreUpdate, pointer_clicks)
+            .add_systems(Update, pick
+                .in_set(ClickSet::Pick)
+                .run_if(in_state(Round::Playing).or_else(in_state(Round::Paused))))
+        ;
+    }
+}
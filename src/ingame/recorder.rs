@@ -0,0 +1,207 @@
+use std::{mem, time::Duration};
+
+use bevy::{
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+
+use super::{
+    ClickSet,
+    RestartRound,
+    picking::FieldClicked,
+};
+use crate::{
+    AppState,
+    Round,
+    BallCount,
+    Click,
+    ClickInput,
+    GameMode,
+    Score,
+    Seed,
+    difficulty::Difficulty,
+    level::LevelProgress,
+    playarea::{ForceLetterbox, PlayArea},
+    replay::{Replay, ReplayClick, ReplayResult},
+};
+
+/// Replay of the current or last round, recorded while it is played.
+#[derive(Resource, Deref, Default, Debug)]
+pub struct Recording(Replay);
+
+/// Fixed steps played in the current round, the clock of every replay.
+#[derive(Resource, Deref, DerefMut, Default, Debug)]
+struct RoundTick(u64);
+
+/// A replay played back in place of the mouse and reticle, from the title
+/// screen until the player goes back to it.
+///
+/// Inserted before the round starts. The first frame of the round runs no
+/// fixed step, for the clicks made before the first step, and every frame
+/// after it exactly one, so a replay plays out the same on every machine and
+/// at every frame rate.
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+    /// How time advanced before the playback, restored when it stops.
+    previous_strategy: Option<TimeUpdateStrategy>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, next: 0, previous_strategy: None }
+    }
+}
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut commands: Commands,
+    (mut recording, mut tick): (ResMut<Recording>, ResMut<RoundTick>),
+    (playback, mut strategy, mut fixed_time): (Option<ResMut<Playback>>, ResMut<TimeUpdateStrategy>, ResMut<Time<Fixed>>),
+    (seed, mode, difficulty): (Res<Seed>, Res<GameMode>, Res<Difficulty>),
+    (progress, play_area): (Option<Res<LevelProgress>>, Res<PlayArea>),
+) {
+    let level = progress.map_or(1, |progress| progress.number());
+    **tick = 0;
+    recording.0 = Replay {
+        seed: **seed,
+        mode: *mode,
+        difficulty: *difficulty,
+        level,
+        play_area: play_area.size,
+        clicks: Vec::new(),
+        result: None,
+    };
+
+    let Some(mut playback) = playback else { return };
+    // the next level after a replayed one is played by hand
+    if playback.replay.level != level {
+        stop(&mut commands, &mut playback, &mut strategy);
+        return
+    }
+    println!("recorder: playing back {} clicks", playback.replay.clicks.len());
+    playback.next = 0;
+    hold_time(&mut strategy, &mut fixed_time);
+}
+
+/// Stops time for the next frame, with no time left over from the frames before.
+fn hold_time(strategy: &mut TimeUpdateStrategy, fixed_time: &mut Time<Fixed>) {
+    *strategy = TimeUpdateStrategy::ManualDuration(Duration::ZERO);
+    let overstep = fixed_time.overstep();
+    fixed_time.discard_overstep(overstep);
+}
+
+/// Holds time for the frame the round starts in.
+fn start_playback(
+    mut playback: ResMut<Playback>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut fixed_time: ResMut<Time<Fixed>>,
+) {
+    playback.previous_strategy = Some(mem::take(&mut *strategy));
+    hold_time(&mut strategy, &mut fixed_time);
+}
+
+fn stop(
+    commands: &mut Commands,
+    playback: &mut Playback,
+    strategy: &mut TimeUpdateStrategy,
+) {
+    println!("recorder: stopped playback");
+    if let Some(previous) = playback.previous_strategy.take() { *strategy = previous }
+    commands.remove_resource::<Playback>();
+    commands.remove_resource::<ForceLetterbox>();
+}
+
+fn stop_playback(
+    mut commands: Commands,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    playback: Option<ResMut<Playback>>,
+) {
+    let Some(mut playback) = playback else { return };
+    stop(&mut commands, &mut playback, &mut strategy);
+}
+
+fn count_tick(
+    mut tick: ResMut<RoundTick>,
+) {
+    **tick += 1;
+}
+
+fn record(
+    mut recording: ResMut<Recording>,
+    mut field_events: EventReader<FieldClicked>,
+    tick: Res<RoundTick>,
+) {
+    for FieldClicked(click) in field_events.read() {
+        recording.0.clicks.push(ReplayClick { tick: **tick, pos: click.pos, player: click.player });
+    }
+}
+
+/// Replaces the clicks of the frame with the ones of the replay at this step,
+/// and steps the next frame by one fixed step.
+fn play(
+    mut clicks: ResMut<ClickInput>,
+    mut playback: ResMut<Playback>,
+    (mut strategy, fixed_time): (ResMut<TimeUpdateStrategy>, Res<Time<Fixed>>),
+    tick: Res<RoundTick>,
+) {
+    *strategy = TimeUpdateStrategy::ManualDuration(fixed_time.timestep());
+
+    // live clicks would change the round
+    clicks.clear();
+
+    let playback = &mut *playback;
+    while let Some(click) = playback.replay.clicks.get(playback.next).filter(|click| click.tick <= **tick) {
+        clicks.push(Click { pos: click.pos, player: click.player });
+        playback.next += 1;
+    }
+}
+
+/// Notes how the round ended, once the results are in.
+fn record_result(
+    mut recording: ResMut<Recording>,
+    state: Res<State<AppState>>,
+    (ball_count, score): (Res<BallCount>, Res<Score>),
+    playback: Option<Res<Playback>>,
+) {
+    if recording.result.is_some() { return }
+
+    let result = ReplayResult {
+        cleared: *state.get() == AppState::Gameclear,
+        ball_count: **ball_count,
+        points: score.points,
+    };
+    println!("recorder: round ended with {:?}", result);
+    recording.0.result = Some(result);
+
+    let Some(playback) = playback else { return };
+    if playback.replay.result.is_some_and(|expected| expected != result) {
+        println!("recorder: playback diverged from {:?}", playback.replay.result);
+    }
+}
+
+pub struct RecorderPlugin;
+
+impl Plugin for RecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Recording>()
+            .init_resource::<RoundTick>()
+            .observe(reset)
+            .add_systems(FixedUpdate, count_tick.run_if(in_state(Round::Playing)))
+            .add_systems(Update, play
+                .after(ClickSet::Input)
+                .before(ClickSet::Pick)
+                .run_if(in_state(Round::Playing))
+                .run_if(resource_exists::<Playback>))
+            .add_systems(Update, record
+                .after(ClickSet::Pick)
+                .run_if(in_state(Round::Playing)))
+            .add_systems(Update, record_result
+                .run_if(in_state(AppState::Gameover).or_else(in_state(AppState::Gameclear))))
+            .add_systems(Last, start_playback.run_if(resource_added::<Playback>))
+            .add_systems(OnEnter(AppState::Mainmenu), stop_playback)
+        ;
+    }
+}
@@ -0,0 +1,132 @@
+use bevy::{
+    prelude::*,
+    sprite::Mesh2dHandle,
+};
+
+use super::{ClickSet, RestartRound};
+use crate::{
+    AppState,
+    Round,
+    Click,
+    ClickInput,
+    GameMode,
+    Player,
+    actions::{Action, ActionInput},
+    gamepad::GamepadInput,
+    playarea::PlayArea,
+};
+
+const SIZE: f32 = 24.0;
+const THICKNESS: f32 = 3.0;
+const COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+// pixels per second with the stick fully tilted
+const SPEED: f32 = 480.0;
+// keys moving the reticle like a stick, with their directions
+const MOVE_KEYS: [(KeyCode, Vec2); 4] = [
+    (KeyCode::ArrowUp, Vec2::Y),
+    (KeyCode::ArrowDown, Vec2::NEG_Y),
+    (KeyCode::ArrowLeft, Vec2::NEG_X),
+    (KeyCode::ArrowRight, Vec2::X),
+];
+
+/// Aim of a gamepad or the arrow keys, popping with the gamepad binding of
+/// `Action::Pop` or with `Action::ReticlePop`; player two's in the two-player mode.
+#[derive(Component)]
+pub struct Reticle;
+
+fn setup(
+    mut commands: Commands,
+) {
+    println!("reticle: setup");
+    commands.spawn((
+        SpatialBundle {
+            transform: Transform::from_xyz(0.0, 0.0, 98.0),
+            // shown once a gamepad is used
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        Reticle,
+    ))
+    .insert(Name::new("reticle"));
+}
+
+/// Back to the center and hidden, also on a retry.
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut query: Query<(&mut Transform, &mut Visibility), With<Reticle>>,
+) {
+    for (mut transform, mut visibility) in query.iter_mut() {
+        transform.translation = Vec2::ZERO.extend(transform.translation.z);
+        *visibility = Visibility::Hidden;
+    }
+}
+
+fn add_mesh(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<Entity, Added<Reticle>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).try_insert((
+            Mesh2dHandle(meshes.add(Annulus::new(SIZE / 2.0 - THICKNESS, SIZE / 2.0))),
+            materials.add(COLOR),
+        ));
+    }
+}
+
+fn update(
+    mut query: Query<(&mut Transform, &mut Visibility), With<Reticle>>,
+    mut clicks: ResMut<ClickInput>,
+    (gamepad, keyboard_input): (GamepadInput, Option<Res<ButtonInput<KeyCode>>>),
+    actions: ActionInput,
+    (play_area, mode): (Res<PlayArea>, Res<GameMode>),
+    round: Res<State<Round>>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut visibility)) = query.get_single_mut() else { return };
+    let keys: Vec2 = MOVE_KEYS.iter()
+        .filter(|(key, _)| keyboard_input.as_ref().is_some_and(|input| input.pressed(*key)))
+        .map(|(_, direction)| *direction)
+        .sum();
+    let stick = (gamepad.left_stick() + keys).clamp_length_max(1.0);
+    let pop = actions.gamepad_just_pressed(Action::Pop) || actions.just_pressed(Action::ReticlePop);
+
+    if stick == Vec2::ZERO && !pop { return }
+    *visibility = Visibility::Visible;
+
+    if *round.get() == Round::Playing {
+        let pos = transform.translation.truncate() + stick * SPEED * time.delta_seconds();
+        let pos = play_area.clamp(pos, 0.0);
+        transform.translation = pos.extend(transform.translation.z);
+    }
+    // popping goes through picking like a click, with the same hit radius
+    if pop {
+        let player = if *mode == GameMode::Versus { Player::Two } else { Player::One };
+        clicks.push(Click { pos: transform.translation.truncate(), player });
+    }
+}
+
+fn despawn(
+    mut commands: Commands,
+    query: Query<Entity, With<Reticle>>,
+) {
+    println!("reticle: despawn");
+    for entity in query.iter() { commands.entity(entity).despawn() }
+}
+
+pub struct ReticlePlugin;
+
+impl Plugin for ReticlePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Ingame), setup)
+            .observe(reset)
+            .add_systems(Update, update
+                .in_set(ClickSet::Input)
+                .run_if(in_state(Round::Playing).or_else(in_state(Round::Paused))))
+            .add_systems(Update, add_mesh.run_if(resource_exists::<AssetServer>))
+            .add_systems(OnExit(AppState::Ingame), despawn)
+        ;
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+
+use super::{
+    ClickSet,
+    RestartRound,
+    balls::BallPopped,
+    picking::ClickMissed,
+};
+use crate::{
+    AppState,
+    Round,
+    BallCount,
+    GameMode,
+    GameTimer,
+    Score,
+};
+
+const POINTS_BALL: f32 = 100.0;
+// a ball this fast is worth twice the points
+const POINTS_SPEED: f32 = 240.0;
+// a ball this large is worth the base points, smaller ones more
+const POINTS_SIZE: f32 = 30.0;
+const COMBO_TIME: f32 = 1.0;
+const COMBO_STEP: f32 = 0.5;
+const COMBO_MAX: f32 = 4.0;
+const TIME_BONUS: f32 = 100.0;
+const MISS_PENALTY: u32 = 50;
+
+/// Pops in a row, each within `COMBO_TIME` of the previous one.
+#[derive(Resource, Default, Debug)]
+pub struct Combo {
+    count: u32,
+    last_pop: Option<f32>,
+}
+
+impl Combo {
+    fn in_time(&self, now: f32) -> bool {
+        self.last_pop.is_some_and(|last_pop| now - last_pop <= COMBO_TIME)
+    }
+
+    /// Pops in the running combo at `now`, zero once it timed out.
+    pub fn current(&self, now: f32) -> u32 {
+        if self.in_time(now) { self.count } else { 0 }
+    }
+}
+
+fn combo_multiplier(combo: u32) -> f32 {
+    (1.0 + COMBO_STEP * combo.saturating_sub(1) as f32).min(COMBO_MAX)
+}
+
+fn pop_points(speed: f32, size: f32, combo: u32) -> u32 {
+    let speed_factor = 1.0 + speed / POINTS_SPEED;
+    let size_factor = POINTS_SIZE / size.max(1.0);
+
+    (POINTS_BALL * speed_factor * size_factor * combo_multiplier(combo)).round() as u32
+}
+
+fn time_bonus(remaining_secs: f32) -> u32 {
+    (remaining_secs.max(0.0) * TIME_BONUS).round() as u32
+}
+
+fn update(
+    mut score: ResMut<Score>,
+    mut combo: ResMut<Combo>,
+    mut popped_events: EventReader<BallPopped>,
+    mut missed_events: EventReader<ClickMissed>,
+    ball_count: Res<BallCount>,
+    timer: Res<GameTimer>,
+    mode: Res<GameMode>,
+) {
+    let now = timer.0.elapsed_secs();
+
+    for _ in missed_events.read() {
+        println!("score: missed, {} points penalty", MISS_PENALTY);
+        score.points = score.points.saturating_sub(MISS_PENALTY);
+        *combo = Combo::default();
+    }
+
+    for popped in popped_events.read() {
+        if !popped.kind.counts() {
+            println!("score: popped a penalty ball, combo lost");
+            *combo = Combo::default();
+            continue
+        }
+        combo.count = if combo.in_time(now) { combo.count + 1 } else { 1 };
+        combo.last_pop = Some(now);
+
+        let points = pop_points(popped.speed, popped.size, combo.count);
+        println!("score: popped for {} points with combo {}", points, combo.count);
+        score.points += points;
+    }
+
+    if mode.has_time_limit() && **ball_count == 0 && score.time_bonus == 0 {
+        score.time_bonus = time_bonus(timer.0.remaining_secs());
+        println!("score: time bonus {} points", score.time_bonus);
+        score.points += score.time_bonus;
+    }
+}
+
+/// Endless rounds earn the time bonus for every second survived.
+fn add_survival_bonus(
+    mut score: ResMut<Score>,
+    timer: Res<GameTimer>,
+) {
+    score.time_bonus = time_bonus(timer.clear_time());
+    println!("score: survival bonus {} points", score.time_bonus);
+    score.points += score.time_bonus;
+}
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut score: ResMut<Score>,
+    mut combo: ResMut<Combo>,
+) {
+    println!("score: reset");
+    *score = Score::default();
+    *combo = Combo::default();
+}
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Score>()
+            .init_resource::<Combo>()
+            .add_systems(Update, update
+                .after(ClickSet::Handle)
+                .run_if(in_state(Round::Playing)))
+            .add_systems(OnEnter(AppState::Gameover), add_survival_bonus
+                .run_if(resource_equals(GameMode::Endless)))
+            .observe(reset)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faster_and_smaller_balls_are_worth_more() {
+        let base = pop_points(0.0, POINTS_SIZE, 1);
+
+        assert_eq!(base, POINTS_BALL as u32);
+        assert_eq!(pop_points(POINTS_SPEED, POINTS_SIZE, 1), base * 2);
+        assert_eq!(pop_points(0.0, POINTS_SIZE / 2.0, 1), base * 2);
+    }
+
+    #[test]
+    fn combo_multiplier_grows_up_to_the_cap() {
+        assert_eq!(combo_multiplier(0), 1.0);
+        assert_eq!(combo_multiplier(1), 1.0);
+        assert_eq!(combo_multiplier(2), 1.0 + COMBO_STEP);
+        assert_eq!(combo_multiplier(100), COMBO_MAX);
+    }
+
+    #[test]
+    fn time_bonus_scales_with_remaining_time() {
+        assert_eq!(time_bonus(0.0), 0);
+        assert_eq!(time_bonus(-1.0), 0);
+        assert_eq!(time_bonus(2.5), (2.5 * TIME_BONUS) as u32);
+    }
+}
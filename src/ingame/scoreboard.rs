@@ -1,29 +1,53 @@
 use bevy::prelude::*;
 
+use super::{
+    colormatch::TargetColor,
+    score::Combo,
+    sequence::NextNumber,
+    spawner::BALL_CAP,
+    splits::Splits,
+    versus::Tallies,
+};
 use crate::{
     PATH_FONT,
     AppState,
-    Config,
+    Round,
     BallCount,
+    GameMode,
     GameTimer,
+    Player,
+    Score,
+    difficulty::Difficulty,
 };
 
 const BALLCOUNT_TEXT: &str = "ボールのこり: ";
 const TIMER_TEXT: &str = " | タイム: ";
+const SCORE_TEXT: &str = " | スコア: ";
+const COMBO_TEXT: &str = "コンボ";
+const TARGET_TEXT: &str = "ねらういろ: ";
+const NEXT_TEXT: &str = "つぎ: ";
 const TEXT_SIZE: f32 = 20.0;
 const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 const TEXT_PADDING: Val = Val::Px(5.0);
+const COMBO_COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+const AHEAD_COLOR: Color = Color::srgb(0.4, 0.9, 0.4);
+const BEHIND_COLOR: Color = Color::srgb(0.9, 0.4, 0.4);
 
 #[derive(Component)]
 struct ScoreboardUi;
 
+/// Line of ball count, time, score and combo.
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct TargetUi;
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    config: Res<Config>,
+    difficulty: Res<Difficulty>,
 ) {
-    if !config.setup_ingame { return }
-
     println!("scoreboard: setup");
     commands.spawn((
         TextBundle::from_sections([
@@ -57,6 +81,35 @@ fn setup(
                     color: TEXT_COLOR,
                 },
             ),
+            TextSection::new(
+                SCORE_TEXT,
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: COMBO_COLOR,
+                },
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            ),
         ])
         .with_style(Style {
             position_type: PositionType::Absolute,
@@ -65,18 +118,129 @@ fn setup(
             ..Default::default()
         }),
         ScoreboardUi,
+        ScoreText,
+    ));
+    // difficulty
+    commands.spawn((
+        TextBundle::from_section(
+            difficulty.label(),
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: TEXT_SIZE,
+                color: TEXT_COLOR,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: TEXT_PADDING,
+            right: TEXT_PADDING,
+            ..Default::default()
+        }),
+        ScoreboardUi,
+    ));
+    // target color, next number or the tallies of both players, filled in
+    // color-match, number-sequence and two-player rounds
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::from_style(
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            ),
+            TextSection::from_style(
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: TEXT_COLOR,
+                },
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(TEXT_SIZE * 1.5),
+            left: TEXT_PADDING,
+            ..Default::default()
+        }),
+        ScoreboardUi,
+        TargetUi,
     ));
 }
 
 fn update(
-    mut query: Query<&mut Text, With<ScoreboardUi>>,
+    mut query: Query<&mut Text, With<ScoreText>>,
     ball_count: Res<BallCount>,
     timer: Res<GameTimer>,
+    score: Res<Score>,
+    (combo, splits): (Res<Combo>, Res<Splits>),
+    mode: Res<GameMode>,
 ) {
-    let mut text = query.single_mut();
-    // write ballcount and timer
-    text.sections[1].value = ball_count.to_string();
-    text.sections[3].value = timer.0.remaining_secs().round().to_string();
+    let Ok(mut text) = query.get_single_mut() else { return };
+    // write ballcount, timer, score and combo
+    match *mode {
+        GameMode::Classic | GameMode::ColorMatch | GameMode::Sequence | GameMode::Versus => {
+            text.sections[1].value = ball_count.to_string();
+            text.sections[3].value = timer.0.remaining_secs().round().to_string();
+        },
+        // endless rounds count up towards the ball cap
+        GameMode::Endless => {
+            text.sections[1].value = format!("{}/{}", **ball_count, BALL_CAP);
+            text.sections[3].value = timer.0.elapsed_secs().floor().to_string();
+        },
+        // time-attack rounds count up, against the personal best
+        GameMode::TimeAttack => {
+            text.sections[1].value = ball_count.to_string();
+            text.sections[3].value = format!("{:.1}", timer.0.elapsed_secs());
+        },
+    }
+    let delta = splits.latest_delta().filter(|_| *mode == GameMode::TimeAttack);
+    let section = &mut text.sections[7];
+    section.value = delta.map_or_else(String::new, |delta| format!(" {:+.2}", delta));
+    section.style.color = if delta.is_some_and(|delta| delta < 0.0) { AHEAD_COLOR } else { BEHIND_COLOR };
+    text.sections[5].value = score.points.to_string();
+    let combo_count = combo.current(timer.0.elapsed_secs());
+    text.sections[6].value = if combo_count > 1 {
+        format!(" {}{}", combo_count, COMBO_TEXT)
+    } else {
+        String::new()
+    };
+}
+
+fn update_target(
+    mut query: Query<&mut Text, With<TargetUi>>,
+    target: Res<TargetColor>,
+) {
+    if !target.is_changed() { return }
+    let Ok(mut text) = query.get_single_mut() else { return };
+
+    text.sections[0].value = TARGET_TEXT.to_string();
+    text.sections[1].value = target.name().to_string();
+    text.sections[1].style.color = target.color();
+}
+
+fn update_next(
+    mut query: Query<&mut Text, With<TargetUi>>,
+    next_number: Res<NextNumber>,
+) {
+    if !next_number.is_changed() { return }
+    let Ok(mut text) = query.get_single_mut() else { return };
+
+    text.sections[0].value = NEXT_TEXT.to_string();
+    text.sections[1].value = next_number.to_string();
+}
+
+fn update_tallies(
+    mut query: Query<&mut Text, With<TargetUi>>,
+    tallies: Res<Tallies>,
+) {
+    if !tallies.is_changed() { return }
+    let Ok(mut text) = query.get_single_mut() else { return };
+
+    for (section, player) in text.sections.iter_mut().zip(Player::ALL) {
+        section.value = format!("{}: {} ", player.label(), tallies.get(player));
+        section.style.color = player.color();
+    }
 }
 
 fn despawn(
@@ -92,10 +256,18 @@ pub struct ScoreboardPlugin;
 impl Plugin for ScoreboardPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(OnEnter(AppState::Ingame), setup)
-            .add_systems(Update, update.run_if(in_state(AppState::Ingame)))
-            .add_systems(OnEnter(AppState::Gameover), despawn)
-            .add_systems(OnEnter(AppState::Gameclear), despawn)
+            .add_systems(OnEnter(AppState::Ingame), setup.run_if(resource_exists::<AssetServer>))
+            .add_systems(Update, update.run_if(in_state(Round::Playing)))
+            .add_systems(Update, update_target
+                .run_if(in_state(Round::Playing))
+                .run_if(resource_exists::<TargetColor>))
+            .add_systems(Update, update_next
+                .run_if(in_state(Round::Playing))
+                .run_if(resource_exists::<NextNumber>))
+            .add_systems(Update, update_tallies
+                .run_if(in_state(Round::Playing))
+                .run_if(resource_exists::<Tallies>))
+            .add_systems(OnExit(AppState::Ingame), despawn)
         ;
     }
 }
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+
+use super::RestartRound;
+use crate::GameMode;
+
+/// Seconds a click on a ball out of order takes off the time limit.
+pub const WRONG_ORDER_PENALTY: f32 = 2.0;
+
+/// Place of a ball in the order of a number-sequence round, from 1.
+#[derive(Component, Deref, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Number(pub u32);
+
+/// Lowest number still on the field, the only one a click may pop.
+///
+/// Moved on by every pop of a numbered ball, also by bombs popping several.
+///
+/// Only exists during number-sequence rounds.
+#[derive(Resource, Deref, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NextNumber(u32);
+
+impl NextNumber {
+    /// Whether a click may pop a ball; balls without a number, like the
+    /// halves of a splitter, can always be popped.
+    pub fn allows(self, number: Option<&Number>) -> bool {
+        number.is_none_or(|number| **number == self.0)
+    }
+
+    /// Moves on to `lowest`, the lowest number left on the field.
+    pub fn advance(&mut self, lowest: Option<Number>) {
+        let Some(lowest) = lowest else { return };
+        if self.0 == *lowest { return }
+
+        println!("sequence: next number {}", *lowest);
+        self.0 = *lowest;
+    }
+}
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut commands: Commands,
+    mode: Res<GameMode>,
+) {
+    if *mode != GameMode::Sequence {
+        commands.remove_resource::<NextNumber>();
+        return
+    }
+    commands.insert_resource(NextNumber(1));
+}
+
+pub struct SequencePlugin;
+
+impl Plugin for SequencePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .observe(reset)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_next_number_may_be_popped() {
+        let next_number = NextNumber(3);
+
+        assert!(next_number.allows(Some(&Number(3))));
+        assert!(!next_number.allows(Some(&Number(4))));
+        assert!(next_number.allows(None));
+    }
+}
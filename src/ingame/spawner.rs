@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
+};
+
+use super::{
+    PhysicsSet,
+    RestartRound,
+    round_ending,
+    balls::{ball_bundle, ball_color},
+};
+use crate::{
+    AppState,
+    Round,
+    BallCount,
+    GameMode,
+    GameRng,
+    level::{BallKind, CurrentLevel},
+    playarea::PlayArea,
+};
+
+/// Most balls the field holds in the endless mode; one more ends the round.
+pub const BALL_CAP: usize = 40;
+const SPAWN_INTERVAL: f32 = 1.5;
+const SPAWN_INTERVAL_MIN: f32 = 0.25;
+// every spawn shortens the wait for the next one by this factor
+const SPAWN_ACCELERATION: f32 = 0.97;
+
+/// Waves of the endless mode: a new ball every time the timer finishes,
+/// each one sooner than the last.
+#[derive(Resource, Debug)]
+struct Spawner {
+    timer: Timer,
+    spawned: usize,
+}
+
+impl Default for Spawner {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(SPAWN_INTERVAL, TimerMode::Repeating),
+            spawned: 0,
+        }
+    }
+}
+
+fn next_interval(interval: f32) -> f32 {
+    (interval * SPAWN_ACCELERATION).max(SPAWN_INTERVAL_MIN)
+}
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut spawner: ResMut<Spawner>,
+) {
+    *spawner = Spawner::default();
+}
+
+fn spawn(
+    mut commands: Commands,
+    mut spawner: ResMut<Spawner>,
+    mut rng: ResMut<GameRng>,
+    mut ball_count: ResMut<BallCount>,
+    (level, play_area): (Res<CurrentLevel>, Res<PlayArea>),
+    time: Res<Time>,
+    mode: Res<GameMode>,
+) {
+    if !spawner.timer.tick(time.delta()).just_finished() { return }
+
+    let size = level.size.sample(&mut **rng);
+    let speed = level.speed.sample(&mut **rng);
+    let bound = play_area.clamp(play_area.half_size(), size);
+    let pos = Vec2::new(
+        rng.gen_range(-bound.x..=bound.x),
+        rng.gen_range(-bound.y..=bound.y),
+    );
+    let die_velocity = Uniform::new_inclusive(-speed, speed);
+    let velocity = Vec2::new(
+        die_velocity.sample(&mut **rng),
+        die_velocity.sample(&mut **rng),
+    );
+    let color = ball_color(&mut **rng, *mode);
+    // above every ball of the level layout and the earlier spawns
+    let z = (level.ball_count + spawner.spawned) as f32;
+
+    commands.spawn(ball_bundle(pos.extend(z), velocity, size, BallKind::Normal, color))
+        .insert(Name::new("ball"));
+    spawner.spawned += 1;
+    **ball_count += 1;
+
+    let interval = next_interval(spawner.timer.duration().as_secs_f32());
+    spawner.timer.set_duration(Duration::from_secs_f32(interval));
+    println!("spawner: spawned ball {}, next in {:.2} seconds", **ball_count, interval);
+}
+
+fn check_cap(
+    mut next_state: ResMut<NextState<AppState>>,
+    ball_count: Res<BallCount>,
+) {
+    if **ball_count <= BALL_CAP { return }
+
+    println!("spawner: moved state to Gameover from Ingame");
+    next_state.set(AppState::Gameover);
+}
+
+pub struct SpawnerPlugin;
+
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Spawner>()
+            .observe(reset)
+            // by fixed steps, and none after the step that ended the round, so a replay
+            // spawns the same balls at any frame rate
+            .add_systems(FixedUpdate, (
+                spawn,
+                check_cap,
+            ).chain()
+                .after(PhysicsSet)
+                .run_if(in_state(Round::Playing))
+                .run_if(not(round_ending))
+                .run_if(resource_equals(GameMode::Endless)))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waves_come_faster_down_to_the_minimum() {
+        let mut interval = SPAWN_INTERVAL;
+        let mut intervals = Vec::new();
+
+        for _ in 0..200 {
+            interval = next_interval(interval);
+            intervals.push(interval);
+        }
+
+        assert!(intervals.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(intervals[0] < SPAWN_INTERVAL);
+        assert_eq!(*intervals.last().unwrap(), SPAWN_INTERVAL_MIN);
+    }
+}
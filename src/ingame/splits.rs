@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+
+use super::{
+    ClickSet,
+    RestartRound,
+    balls::BallPopped,
+};
+use crate::{
+    Round,
+    GameMode,
+    GameTimer,
+    difficulty::Difficulty,
+    highscore::BestSplits,
+    level::CurrentLevel,
+};
+
+/// Times of the pops of a time-attack round, next to the personal best.
+#[derive(Resource, Default, Debug)]
+pub struct Splits {
+    /// Seconds into the round of every pop of a ball that counts.
+    pub times: Vec<f32>,
+    /// Splits of the best clear of this level at this difficulty, taken when
+    /// the round started.
+    pub best: Option<Vec<f32>>,
+}
+
+impl Splits {
+    /// Seconds split `index` is behind the personal best, negative when ahead.
+    pub fn delta(&self, index: usize) -> Option<f32> {
+        let best = self.best.as_ref()?.get(index)?;
+        Some(self.times.get(index)? - best)
+    }
+
+    /// Delta of the latest split, shown live while playing.
+    pub fn latest_delta(&self) -> Option<f32> {
+        self.delta(self.times.len().checked_sub(1)?)
+    }
+
+    /// Whether the round beat the personal best, or set the first one.
+    pub fn is_best(&self) -> bool {
+        let Some(time) = self.times.last() else { return false };
+        self.best.as_ref().and_then(|best| best.last()).is_none_or(|best| time < best)
+    }
+}
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut splits: ResMut<Splits>,
+    level: Res<CurrentLevel>,
+    difficulty: Res<Difficulty>,
+    best_splits: Option<Res<BestSplits>>,
+) {
+    let best = best_splits.and_then(|best_splits| best_splits.get(&level.name, *difficulty).cloned());
+    println!("splits: reset, personal best {:?}", best.as_ref().and_then(|best| best.last()));
+    *splits = Splits { times: Vec::new(), best };
+}
+
+fn record(
+    mut splits: ResMut<Splits>,
+    mut popped_events: EventReader<BallPopped>,
+    timer: Res<GameTimer>,
+) {
+    for popped in popped_events.read() {
+        if !popped.kind.counts() { continue }
+        splits.times.push(timer.0.elapsed_secs());
+    }
+}
+
+pub struct SplitsPlugin;
+
+impl Plugin for SplitsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Splits>()
+            .observe(reset)
+            .add_systems(Update, record
+                .after(ClickSet::Handle)
+                .run_if(in_state(Round::Playing))
+                .run_if(resource_equals(GameMode::TimeAttack)))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deltas_compare_against_the_personal_best() {
+        let mut splits = Splits { times: vec![1.0, 2.5], best: Some(vec![1.5, 2.0, 3.0]) };
+
+        assert_eq!(splits.delta(0), Some(-0.5));
+        assert_eq!(splits.latest_delta(), Some(0.5));
+        assert_eq!(splits.delta(2), None);
+
+        splits.times.push(2.9);
+        assert!(splits.is_best());
+        splits.times[2] = 3.1;
+        assert!(!splits.is_best());
+    }
+
+    #[test]
+    fn first_clear_is_the_personal_best() {
+        let splits = Splits { times: vec![4.0], best: None };
+
+        assert_eq!(splits.latest_delta(), None);
+        assert!(splits.is_best());
+        assert!(!Splits::default().is_best());
+    }
+}
@@ -0,0 +1,1143 @@
+use std::{fs, time::Duration};
+
+use bevy::{
+    prelude::*,
+    input::{
+        gamepad::{
+            GamepadAxisChangedEvent,
+            GamepadButtonChangedEvent,
+            GamepadConnection,
+            GamepadConnectionEvent,
+            GamepadEvent,
+            GamepadInfo,
+        },
+        InputPlugin,
+    },
+    state::app::StatesPlugin,
+    render::camera::{camera_system, ManualTextureViews},
+    time::TimeUpdateStrategy,
+    window::{PrimaryWindow, WindowCreated, WindowFocused, WindowResized, WindowScaleFactorChanged},
+};
+use serde::Deserialize;
+
+use crate::{
+    BALL_COUNT,
+    CURSOR_RANGE,
+    GAMETIME_LIMIT,
+    AppState,
+    Round,
+    BallCount,
+    GameTimer,
+    Score,
+    Click,
+    ClickInput,
+    Player,
+    Seed,
+    GameMode,
+    GameRng,
+    actions::{Action, ActionMap, Binding},
+    difficulty::Difficulty,
+    highscore::BestSplits,
+    replay::Replay,
+    level::{BallKind, CurrentLevel, Layout, Level, Spread},
+    playarea::{Letterbox, PlayArea, PlayAreaPlugin},
+};
+use super::{
+    IngamePlugin,
+    Playback,
+    Recording,
+    RestartRound,
+    balls::{Ball, BallColor, Position, PALETTE},
+    colormatch::{TargetColor, WRONG_COLOR_PENALTY},
+    picking::{Pickable, UiClicked},
+    sequence::{NextNumber, Number, WRONG_ORDER_PENALTY},
+    Splits,
+    Tallies,
+    reticle::Reticle,
+    spawner::BALL_CAP,
+};
+
+const FRAME_TIME: f64 = 1.0 / 60.0;
+const FIXED_TIME: f64 = 1.0 / 60.0;
+const MAX_FRAMES: usize = 10_000;
+const SEED: u64 = 20240901;
+
+fn headless_app() -> App {
+    seeded_app(SEED)
+}
+
+fn seeded_app(seed: u64) -> App {
+    let mut app = build_app(seed, FRAME_TIME);
+    start_round(&mut app);
+    app
+}
+
+fn build_app(seed: u64, frame_time: f64) -> App {
+    let mut app = App::new();
+
+    app
+        .add_plugins((MinimalPlugins, StatesPlugin))
+        .insert_state(AppState::Mainmenu)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(frame_time)))
+        .insert_resource(Time::<Fixed>::from_seconds(FIXED_TIME))
+        .insert_resource(BallCount(BALL_COUNT))
+        .insert_resource(Seed::pinned(seed))
+        .insert_resource(GameRng::new(Seed::pinned(seed)))
+        .insert_resource(GameTimer(
+            Timer::from_seconds(GAMETIME_LIMIT, TimerMode::Once)
+        ))
+        .add_plugins(IngamePlugin);
+    app
+}
+
+/// Leaves the main menu like a click on the title screen would.
+fn start_round(app: &mut App) {
+    app.update();
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Ingame);
+    app.update();
+}
+
+fn state(app: &App) -> AppState {
+    app.world().resource::<State<AppState>>().get().clone()
+}
+
+fn round(app: &App) -> Option<Round> {
+    app.world().get_resource::<State<Round>>().map(|round| round.get().clone())
+}
+
+fn ball_positions(app: &mut App) -> Vec<Vec2> {
+    app.world_mut()
+        .query_filtered::<&Transform, With<Ball>>()
+        .iter(app.world())
+        .map(|transform| transform.translation.truncate())
+        .collect()
+}
+
+fn click(app: &mut App, pos: Vec2) {
+    app.world_mut().resource_mut::<ClickInput>().push(pos.into());
+    app.update();
+}
+
+#[test]
+fn setup_spawns_every_ball() {
+    let mut app = headless_app();
+
+    assert_eq!(state(&app), AppState::Ingame);
+    assert_eq!(ball_positions(&mut app).len(), BALL_COUNT);
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+}
+
+#[test]
+fn popping_every_ball_reaches_gameclear() {
+    let mut app = headless_app();
+
+    for _ in 0..MAX_FRAMES {
+        let Some(&target) = ball_positions(&mut app).first() else { break };
+        click(&mut app, target);
+    }
+    app.update();
+
+    assert_eq!(state(&app), AppState::Gameclear);
+    assert_eq!(**app.world().resource::<BallCount>(), 0);
+    assert!(ball_positions(&mut app).is_empty());
+    assert!(app.world().resource::<GameTimer>().0.remaining_secs() > 0.0);
+
+    let score = app.world().resource::<Score>();
+    assert!(score.time_bonus > 0);
+    assert!(score.points > score.time_bonus);
+}
+
+#[test]
+fn idle_round_reaches_gameover() {
+    let mut app = headless_app();
+
+    for _ in 0..MAX_FRAMES {
+        if state(&app) != AppState::Ingame { break }
+        app.update();
+    }
+    app.update();
+
+    assert_eq!(state(&app), AppState::Gameover);
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+    assert!(ball_positions(&mut app).is_empty());
+}
+
+#[test]
+fn click_on_empty_space_pops_nothing() {
+    let mut app = headless_app();
+    let corner = Vec2::new(-1000.0, 1000.0);
+
+    click(&mut app, corner);
+
+    assert_eq!(state(&app), AppState::Ingame);
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+    assert_eq!(ball_positions(&mut app).len(), BALL_COUNT);
+}
+
+#[test]
+fn clicks_last_only_one_frame() {
+    let mut app = headless_app();
+
+    app.world_mut().resource_mut::<ClickInput>().push(Vec2::ZERO.into());
+    app.update();
+
+    assert!(app.world().resource::<ClickInput>().is_empty());
+}
+
+#[test]
+fn retry_after_gameover_starts_a_fresh_round() {
+    let mut app = headless_app();
+
+    for _ in 0..MAX_FRAMES {
+        if state(&app) != AppState::Ingame { break }
+        app.update();
+    }
+    app.update();
+    assert_eq!(state(&app), AppState::Gameover);
+
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Ingame);
+    app.update();
+
+    assert_eq!(state(&app), AppState::Ingame);
+    assert_eq!(ball_positions(&mut app).len(), BALL_COUNT);
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+    assert!(app.world().resource::<GameTimer>().0.remaining_secs() > GAMETIME_LIMIT - 1.0);
+}
+
+#[test]
+fn same_seed_replays_the_same_round() {
+    let mut app_1 = seeded_app(SEED);
+    let mut app_2 = seeded_app(SEED);
+
+    for _ in 0..300 {
+        app_1.update();
+        app_2.update();
+    }
+
+    assert_eq!(ball_positions(&mut app_1), ball_positions(&mut app_2));
+}
+
+#[test]
+fn different_seeds_move_balls_differently() {
+    let mut app_1 = seeded_app(SEED);
+    let mut app_2 = seeded_app(SEED + 1);
+
+    for _ in 0..60 {
+        app_1.update();
+        app_2.update();
+    }
+
+    assert_ne!(ball_positions(&mut app_1), ball_positions(&mut app_2));
+}
+
+/// Plays `app` to the results and starts the next round.
+fn retry(app: &mut App) {
+    for _ in 0..MAX_FRAMES {
+        if state(app) != AppState::Ingame { break }
+        app.update();
+    }
+    app.update();
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Ingame);
+    app.update();
+    app.update();
+}
+
+#[test]
+fn retry_keeps_a_pinned_seed() {
+    let mut app = headless_app();
+    app.update();
+    let first_round = ball_positions(&mut app);
+
+    retry(&mut app);
+
+    assert_eq!(**app.world().resource::<Seed>(), SEED);
+    assert_eq!(ball_positions(&mut app), first_round);
+}
+
+#[test]
+fn retry_draws_a_fresh_seed_unless_pinned() {
+    let mut app = build_app(SEED, FRAME_TIME);
+    app.insert_resource(Seed::random());
+    start_round(&mut app);
+    app.update();
+    let first_seed = *app.world().resource::<Seed>();
+    let first_round = ball_positions(&mut app);
+    assert_eq!(app.world().resource::<Recording>().seed, *first_seed);
+
+    retry(&mut app);
+
+    let seed = *app.world().resource::<Seed>();
+    assert!(!seed.pinned);
+    assert_ne!(seed, first_seed);
+    assert_eq!(app.world().resource::<Recording>().seed, *seed);
+    assert_ne!(ball_positions(&mut app), first_round);
+}
+
+#[test]
+fn missed_click_costs_points_but_never_goes_negative() {
+    let mut app = headless_app();
+    let corner = Vec2::new(-1000.0, 1000.0);
+
+    click(&mut app, corner);
+    assert_eq!(app.world().resource::<Score>().points, 0);
+
+    let target = ball_positions(&mut app)[0];
+    click(&mut app, target);
+    let points = app.world().resource::<Score>().points;
+    assert!(points > 0);
+
+    click(&mut app, corner);
+    assert_eq!(app.world().resource::<Score>().points, points.saturating_sub(50));
+}
+
+#[test]
+fn score_resets_on_retry() {
+    let mut app = headless_app();
+    let target = ball_positions(&mut app)[0];
+    click(&mut app, target);
+    assert!(app.world().resource::<Score>().points > 0);
+
+    for _ in 0..MAX_FRAMES {
+        if state(&app) != AppState::Ingame { break }
+        app.update();
+    }
+    app.update();
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Ingame);
+    app.update();
+
+    assert_eq!(app.world().resource::<Score>().points, 0);
+}
+
+#[test]
+fn round_follows_the_current_level() {
+    let mut app = headless_app();
+    let level = Level {
+        size: Spread::Fixed(40.0),
+        time_limit: 2.0,
+        ..still_level(vec![(-200.0, 0.0), (0.0, 0.0), (200.0, 0.0)], vec![])
+    };
+    app.world_mut().insert_resource(CurrentLevel(level));
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Gameover);
+    app.update();
+    app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Ingame);
+    app.update();
+
+    assert_eq!(ball_positions(&mut app), vec![
+        Vec2::new(-200.0, 0.0),
+        Vec2::new(0.0, 0.0),
+        Vec2::new(200.0, 0.0),
+    ]);
+    assert_eq!(**app.world().resource::<BallCount>(), 3);
+
+    for _ in 0..(3.0 / FRAME_TIME) as usize { app.update(); }
+
+    assert_eq!(state(&app), AppState::Gameover);
+    assert_eq!(**app.world().resource::<BallCount>(), 3);
+}
+
+/// Ball positions after every physics step.
+#[derive(Resource, Default)]
+struct Trajectory(Vec<Vec<Vec2>>);
+
+fn record_trajectory(
+    mut trajectory: ResMut<Trajectory>,
+    query: Query<&Position, With<Ball>>,
+) {
+    trajectory.0.push(query.iter().map(|position| **position).collect());
+}
+
+#[test]
+fn trajectories_do_not_depend_on_the_frame_rate() {
+    const STEPS: usize = 180;
+
+    let trajectories: Vec<Vec<Vec<Vec2>>> = [30.0, 60.0, 144.0]
+        .into_iter()
+        .map(|fps| {
+            let mut app = build_app(SEED, 1.0 / fps);
+            app
+                .init_resource::<Trajectory>()
+                .add_systems(FixedPostUpdate, record_trajectory.run_if(in_state(AppState::Ingame)));
+            start_round(&mut app);
+
+            while app.world().resource::<Trajectory>().0.len() < STEPS { app.update(); }
+
+            let mut trajectory = app.world_mut().remove_resource::<Trajectory>().unwrap().0;
+            trajectory.truncate(STEPS);
+            trajectory
+        })
+        .collect();
+
+    assert_eq!(trajectories[0], trajectories[1]);
+    assert_eq!(trajectories[1], trajectories[2]);
+}
+
+#[test]
+fn rendered_balls_are_interpolated_between_steps() {
+    let mut app = build_app(SEED, FIXED_TIME / 4.0);
+    start_round(&mut app);
+    // eleven quarter steps in, three quarters into the next step
+    for _ in 0..9 { app.update(); }
+
+    let fraction = app.world().resource::<Time<Fixed>>().overstep_fraction();
+    let rendered = ball_positions(&mut app);
+    let physics: Vec<Vec2> = app.world_mut()
+        .query_filtered::<&Position, With<Ball>>()
+        .iter(app.world())
+        .map(|position| **position)
+        .collect();
+
+    assert!(fraction > 0.0 && fraction < 1.0);
+    assert_ne!(rendered, physics);
+}
+
+#[test]
+fn balls_stay_inside_a_shrunk_play_area() {
+    let mut app = headless_app();
+    let play_area = PlayArea::fit(Vec2::new(320.0, 240.0), false);
+    app.insert_resource(play_area);
+
+    for _ in 0..120 { app.update(); }
+
+    for (pos, size) in ball_sizes(&mut app) {
+        assert_eq!(play_area.clamp(pos, size), pos);
+    }
+}
+
+#[test]
+fn ui_takes_clicks_before_the_balls() {
+    let mut app = headless_app();
+    let target = ball_positions(&mut app)[0];
+    let button = app.world_mut()
+        .spawn((Transform::from_translation(target.extend(99.0)), Pickable::ui(20.0)))
+        .id();
+
+    click(&mut app, target);
+
+    let events = app.world().resource::<Events<UiClicked>>();
+    assert!(events.get_reader().read(events).any(|clicked| clicked.entity == button));
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+    assert_eq!(app.world().resource::<Score>().points, 0);
+}
+
+#[test]
+fn several_taps_in_one_frame_pop_several_balls() {
+    let mut app = headless_app();
+    let positions = ball_positions(&mut app);
+
+    // two fingers landing on different balls in the same frame
+    app.world_mut().resource_mut::<ClickInput>().extend([positions[0], positions[positions.len() - 1]].map(Click::from));
+    app.update();
+
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT - 2);
+}
+
+fn gamepad_app() -> (App, Gamepad) {
+    let mut app = round_app(GameMode::Classic, Level::default());
+    app.add_plugins(InputPlugin);
+
+    let gamepad = Gamepad::new(0);
+    let info = GamepadInfo { name: "test".to_string() };
+    app.world_mut().send_event(GamepadEvent::Connection(
+        GamepadConnectionEvent::new(gamepad, GamepadConnection::Connected(info))
+    ));
+    app.update();
+    (app, gamepad)
+}
+
+fn reticle_position(app: &mut App) -> Vec2 {
+    app.world_mut()
+        .query_filtered::<&Transform, With<Reticle>>()
+        .single(app.world())
+        .translation
+        .truncate()
+}
+
+#[test]
+fn gamepad_stick_moves_the_reticle() {
+    let (mut app, gamepad) = gamepad_app();
+    assert_eq!(reticle_position(&mut app), Vec2::ZERO);
+
+    app.world_mut().send_event(GamepadEvent::Axis(
+        GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickX, 1.0)
+    ));
+    for _ in 0..10 { app.update(); }
+
+    let pos = reticle_position(&mut app);
+    assert!(pos.x > 0.0);
+    assert_eq!(pos.y, 0.0);
+}
+
+#[test]
+fn retry_puts_the_reticle_back() {
+    let mut app = headless_app();
+    app.world_mut().get_resource_or_insert_with(ButtonInput::<KeyCode>::default).press(KeyCode::ArrowRight);
+    for _ in 0..10 { app.update(); }
+    app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::ArrowRight);
+    assert!(reticle_position(&mut app).x > 0.0);
+
+    app.world_mut().trigger(RestartRound);
+    app.update();
+
+    assert_eq!(reticle_position(&mut app), Vec2::ZERO);
+    let visibility = *app.world_mut().query_filtered::<&Visibility, With<Reticle>>().single(app.world());
+    assert_eq!(visibility, Visibility::Hidden);
+}
+
+#[test]
+fn gamepad_button_pops_the_ball_under_the_reticle() {
+    let (mut app, gamepad) = gamepad_app();
+    let reticle = reticle_position(&mut app);
+    let under_reticle = ball_sizes(&mut app).iter()
+        .filter(|(pos, size)| pos.distance(reticle) < size / 2.0 + CURSOR_RANGE)
+        .count();
+    assert!(under_reticle > 0);
+
+    app.world_mut().send_event(GamepadEvent::Button(
+        GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::South, 1.0)
+    ));
+    app.update();
+
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT - under_reticle);
+}
+
+#[test]
+fn rebound_gamepad_button_pops_instead_of_the_default() {
+    let (mut app, gamepad) = gamepad_app();
+    app.world_mut()
+        .resource_mut::<ActionMap>()
+        .rebind(Action::Pop, Binding::Gamepad(GamepadButtonType::North));
+
+    app.world_mut().send_event(GamepadEvent::Button(
+        GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::South, 1.0)
+    ));
+    app.update();
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+
+    app.world_mut().send_event(GamepadEvent::Button(
+        GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::North, 1.0)
+    ));
+    app.update();
+    assert!(**app.world().resource::<BallCount>() < BALL_COUNT);
+}
+
+fn press_key(app: &mut App, key: KeyCode) {
+    app.world_mut().get_resource_or_insert_with(ButtonInput::<KeyCode>::default).press(key);
+    app.update();
+
+    let mut keyboard_input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+    keyboard_input.release(key);
+    keyboard_input.clear();
+}
+
+fn ball_visibilities(app: &mut App) -> Vec<Visibility> {
+    app.world_mut()
+        .query_filtered::<&Visibility, With<Ball>>()
+        .iter(app.world())
+        .copied()
+        .collect()
+}
+
+#[test]
+fn escape_pauses_and_resumes_the_round() {
+    let mut app = headless_app();
+
+    press_key(&mut app, KeyCode::Escape);
+    app.update();
+    assert_eq!(round(&app), Some(Round::Paused));
+    assert!(ball_visibilities(&mut app).iter().all(|visibility| *visibility == Visibility::Hidden));
+    let paused_positions = ball_positions(&mut app);
+
+    for _ in 0..10 { app.update(); }
+    assert_eq!(ball_positions(&mut app), paused_positions);
+
+    press_key(&mut app, KeyCode::KeyP);
+    app.update();
+    assert_eq!(round(&app), Some(Round::Playing));
+    assert_eq!(ball_visibilities(&mut app).len(), BALL_COUNT);
+    assert!(ball_visibilities(&mut app).iter().all(|visibility| *visibility == Visibility::Inherited));
+}
+
+#[test]
+fn losing_focus_pauses_the_round() {
+    let mut app = headless_app();
+
+    app.world_mut().send_event(WindowFocused { window: Entity::PLACEHOLDER, focused: false });
+    app.update();
+    app.update();
+
+    assert_eq!(round(&app), Some(Round::Paused));
+}
+
+#[test]
+fn retry_from_the_pause_menu_starts_a_fresh_round() {
+    let mut app = headless_app();
+    let target = ball_positions(&mut app)[0];
+    click(&mut app, target);
+    assert!(**app.world().resource::<BallCount>() < BALL_COUNT);
+
+    press_key(&mut app, KeyCode::Escape);
+    app.update();
+    press_key(&mut app, KeyCode::KeyR);
+    app.update();
+
+    assert_eq!(state(&app), AppState::Ingame);
+    assert_eq!(ball_positions(&mut app).len(), BALL_COUNT);
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+    assert_eq!(app.world().resource::<Score>().points, 0);
+}
+
+#[test]
+fn back_to_title_from_the_pause_menu_ends_the_round() {
+    let mut app = headless_app();
+
+    press_key(&mut app, KeyCode::Escape);
+    app.update();
+    press_key(&mut app, KeyCode::KeyB);
+    app.update();
+
+    assert_eq!(state(&app), AppState::Mainmenu);
+    assert!(ball_positions(&mut app).is_empty());
+}
+
+#[test]
+fn settings_from_the_pause_menu_keep_the_round() {
+    let mut app = headless_app();
+    let target = ball_positions(&mut app)[0];
+    click(&mut app, target);
+    let ball_count = **app.world().resource::<BallCount>();
+
+    press_key(&mut app, KeyCode::Escape);
+    app.update();
+    press_key(&mut app, KeyCode::KeyS);
+    app.update();
+    assert_eq!(state(&app), AppState::Ingame);
+    assert_eq!(round(&app), Some(Round::Settings));
+
+    app.world_mut().resource_mut::<NextState<Round>>().set(Round::Paused);
+    app.update();
+    press_key(&mut app, KeyCode::Escape);
+    app.update();
+
+    assert_eq!(round(&app), Some(Round::Playing));
+    assert_eq!(ball_positions(&mut app).len(), ball_count);
+    assert_eq!(**app.world().resource::<BallCount>(), ball_count);
+}
+
+#[derive(Resource, Default)]
+struct Restarts(usize);
+
+#[test]
+fn restart_round_resets_every_observer() {
+    let mut app = headless_app();
+    app.init_resource::<Restarts>()
+        .observe(|_trigger: Trigger<RestartRound>, mut restarts: ResMut<Restarts>| restarts.0 += 1);
+    let target = ball_positions(&mut app)[0];
+    click(&mut app, target);
+    for _ in 0..60 { app.update(); }
+
+    app.world_mut().trigger(RestartRound);
+    app.update();
+
+    assert_eq!(app.world().resource::<Restarts>().0, 1);
+    assert_eq!(ball_positions(&mut app).len(), BALL_COUNT);
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_COUNT);
+    assert_eq!(app.world().resource::<Score>().points, 0);
+    assert!(app.world().resource::<GameTimer>().0.remaining_secs() > GAMETIME_LIMIT - 0.1);
+}
+
+fn ball_sizes(app: &mut App) -> Vec<(Vec2, f32)> {
+    app.world_mut()
+        .query_filtered::<&Transform, With<Ball>>()
+        .iter(app.world())
+        .map(|transform| (transform.translation.truncate(), transform.scale.x))
+        .collect()
+}
+
+#[test]
+fn ball_sizes_vary_within_the_level_range() {
+    let mut app = headless_app();
+    let level = Level {
+        speed: Spread::Range(50.0, 100.0),
+        size: Spread::Range(20.0, 40.0),
+        ..Default::default()
+    };
+    app.world_mut().insert_resource(CurrentLevel(level));
+    app.world_mut().trigger(RestartRound);
+    app.update();
+
+    let sizes: Vec<f32> = ball_sizes(&mut app).into_iter().map(|(_, size)| size).collect();
+    assert_eq!(sizes.len(), BALL_COUNT);
+    assert!(sizes.iter().all(|size| (20.0..=40.0).contains(size)));
+    assert!(sizes.iter().any(|size| *size != sizes[0]));
+}
+
+#[test]
+fn hit_radius_follows_the_ball_size() {
+    let mut app = level_app(vec![(0.0, 0.0), (200.0, 0.0)], vec![]);
+    // the ball at the origin grows to 60, the other shrinks to 10
+    let mut query = app.world_mut().query_filtered::<&mut Transform, With<Ball>>();
+    for mut transform in query.iter_mut(app.world_mut()) {
+        let size = if transform.translation.x == 0.0 { 60.0 } else { 10.0 };
+        transform.scale = Vec3::new(size, size, 1.0);
+    }
+    let offset = 30.0 + CURSOR_RANGE - 1.0;
+
+    click(&mut app, Vec2::new(200.0 + offset, 0.0));
+    assert_eq!(**app.world().resource::<BallCount>(), 2);
+
+    click(&mut app, Vec2::new(offset, 0.0));
+    assert_eq!(**app.world().resource::<BallCount>(), 1);
+}
+
+#[test]
+fn easier_difficulties_forgive_near_misses() {
+    let mut app = level_app(vec![(0.0, 0.0), (200.0, 0.0)], vec![]);
+    // outside the normal cursor range of the 30 pixel balls, inside the easy one
+    let near_miss = 15.0 + CURSOR_RANGE + 2.0;
+
+    click(&mut app, Vec2::new(near_miss, 0.0));
+    assert_eq!(**app.world().resource::<BallCount>(), 2);
+
+    app.insert_resource(Difficulty::Easy);
+    click(&mut app, Vec2::new(near_miss, 0.0));
+    assert_eq!(**app.world().resource::<BallCount>(), 1);
+}
+
+#[test]
+fn endless_round_ends_when_the_field_overflows() {
+    let mut app = round_app(GameMode::Endless, Level::default());
+
+    for _ in 0..MAX_FRAMES {
+        if state(&app) != AppState::Ingame { break }
+        app.update();
+    }
+
+    assert_eq!(state(&app), AppState::Gameover);
+    assert_eq!(**app.world().resource::<BallCount>(), BALL_CAP + 1);
+    // no pops, so every point is the survival bonus
+    let score = app.world().resource::<Score>();
+    assert!(score.time_bonus > 0);
+    assert_eq!(score.points, score.time_bonus);
+}
+
+#[test]
+fn endless_round_goes_on_after_the_field_is_cleared() {
+    let mut app = round_app(GameMode::Endless, Level::default());
+
+    for _ in 0..MAX_FRAMES {
+        let Some(&target) = ball_positions(&mut app).first() else { break };
+        click(&mut app, target);
+    }
+    assert_eq!(**app.world().resource::<BallCount>(), 0);
+
+    for _ in 0..(3.0 / FRAME_TIME) as usize { app.update(); }
+
+    assert_eq!(state(&app), AppState::Ingame);
+    assert!(**app.world().resource::<BallCount>() > 0);
+}
+
+#[test]
+fn time_attack_has_no_time_limit_and_records_splits() {
+    let mut app = build_app(SEED, FRAME_TIME);
+    let mut best_splits = BestSplits::default();
+    let best: Vec<f32> = (1..=BALL_COUNT).map(|pop| pop as f32).collect();
+    best_splits.offer("", Difficulty::Normal, &best);
+    app.insert_resource(best_splits);
+    app.insert_resource(GameMode::TimeAttack);
+    start_round(&mut app);
+
+    // well past the classic time limit
+    for _ in 0..((GAMETIME_LIMIT + 5.0) as f64 / FRAME_TIME) as usize { app.update(); }
+    assert_eq!(state(&app), AppState::Ingame);
+
+    for _ in 0..MAX_FRAMES {
+        let Some(&target) = ball_positions(&mut app).first() else { break };
+        click(&mut app, target);
+    }
+    app.update();
+
+    assert_eq!(state(&app), AppState::Gameclear);
+    let splits = app.world().resource::<Splits>();
+    assert_eq!(splits.times.len(), BALL_COUNT);
+    assert!(splits.times.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(splits.times[0] > GAMETIME_LIMIT);
+    // far behind a best of one pop per second
+    assert!(splits.latest_delta().is_some_and(|delta| delta > 0.0));
+    assert!(!splits.is_best());
+}
+
+/// Still balls of a color-match round, with their colors.
+fn color_match_app() -> (App, Vec<(Vec2, Color)>) {
+    let positions = vec![(-250.0, 0.0), (-150.0, 0.0), (-50.0, 0.0), (50.0, 0.0), (150.0, 0.0), (250.0, 0.0)];
+    let mut app = mode_app(GameMode::ColorMatch, positions, vec![]);
+
+    let balls = app.world_mut()
+        .query_filtered::<(&Transform, &BallColor), With<Ball>>()
+        .iter(app.world())
+        .map(|(transform, color)| (transform.translation.truncate(), **color))
+        .collect();
+    (app, balls)
+}
+
+#[test]
+fn color_match_balls_take_palette_colors() {
+    let (app, balls) = color_match_app();
+    let target = *app.world().resource::<TargetColor>();
+
+    assert!(balls.iter().all(|(_, color)| PALETTE.iter().any(|(palette_color, _)| palette_color == color)));
+    assert!(balls.iter().any(|(_, color)| *color == target.color()));
+}
+
+#[test]
+fn color_match_pops_only_the_target_color() {
+    let (mut app, balls) = color_match_app();
+    let target = app.world().resource::<TargetColor>().color();
+    let remaining = |app: &App| app.world().resource::<GameTimer>().0.remaining_secs();
+    let &(wrong, _) = balls.iter().find(|(_, color)| *color != target).unwrap();
+    let &(right, _) = balls.iter().find(|(_, color)| *color == target).unwrap();
+    let before = remaining(&app);
+
+    click(&mut app, wrong);
+    assert_eq!(**app.world().resource::<BallCount>(), balls.len());
+    assert!(remaining(&app) < before - WRONG_COLOR_PENALTY + 0.1);
+
+    click(&mut app, right);
+    assert_eq!(**app.world().resource::<BallCount>(), balls.len() - 1);
+}
+
+fn numbered_balls(app: &mut App) -> Vec<(u32, Vec2)> {
+    let mut balls: Vec<(u32, Vec2)> = app.world_mut()
+        .query_filtered::<(&Number, &Transform), With<Ball>>()
+        .iter(app.world())
+        .map(|(number, transform)| (**number, transform.translation.truncate()))
+        .collect();
+    balls.sort_by_key(|(number, _)| *number);
+    balls
+}
+
+/// `mode_app` in the number-sequence mode.
+fn sequence_app(positions: Vec<(f32, f32)>, kinds: Vec<(BallKind, usize)>) -> App {
+    mode_app(GameMode::Sequence, positions, kinds)
+}
+
+#[test]
+fn sequence_pops_balls_in_order_of_their_numbers() {
+    let mut app = sequence_app(vec![(-200.0, 0.0), (0.0, 0.0), (200.0, 0.0)], vec![]);
+    let balls = numbered_balls(&mut app);
+    assert_eq!(balls.iter().map(|(number, _)| *number).collect::<Vec<_>>(), vec![1, 2, 3]);
+    let remaining = |app: &App| app.world().resource::<GameTimer>().0.remaining_secs();
+    let before = remaining(&app);
+
+    click(&mut app, balls[1].1);
+    assert_eq!(**app.world().resource::<BallCount>(), 3);
+    assert!(remaining(&app) < before - WRONG_ORDER_PENALTY + 0.1);
+
+    click(&mut app, balls[0].1);
+    app.update();
+    assert_eq!(**app.world().resource::<BallCount>(), 2);
+    assert_eq!(**app.world().resource::<NextNumber>(), 2);
+
+    click(&mut app, balls[1].1);
+    click(&mut app, balls[2].1);
+    app.update();
+    assert_eq!(state(&app), AppState::Gameclear);
+}
+
+#[test]
+fn sequence_numbers_only_the_balls_that_count() {
+    let mut app = sequence_app(vec![(-200.0, 0.0), (0.0, 0.0), (200.0, 0.0)], vec![(BallKind::PenaltyTime(5.0), 1)]);
+    let penalty = balls_of(&mut app, BallKind::PenaltyTime(5.0));
+    let balls = numbered_balls(&mut app);
+
+    assert_eq!(balls.iter().map(|(number, _)| *number).collect::<Vec<_>>(), vec![1, 2]);
+    assert!(balls.iter().all(|(_, pos)| *pos != penalty[0]));
+    let remaining = |app: &App| app.world().resource::<GameTimer>().0.remaining_secs();
+    let before = remaining(&app);
+
+    // the penalty ball is left alone and never holds up the order
+    for (_, pos) in balls { click(&mut app, pos); }
+    app.update();
+    assert_eq!(state(&app), AppState::Gameclear);
+    assert!(remaining(&app) > before - 1.0);
+}
+
+#[test]
+fn sequence_click_over_several_numbers_pops_them_in_order() {
+    let mut app = sequence_app(vec![(0.0, 0.0), (12.0, 0.0)], vec![]);
+    let remaining = |app: &App| app.world().resource::<GameTimer>().0.remaining_secs();
+    let before = remaining(&app);
+
+    click(&mut app, Vec2::new(6.0, 0.0));
+    app.update();
+    assert_eq!(state(&app), AppState::Gameclear);
+    assert!(remaining(&app) > before - 1.0);
+}
+
+#[test]
+fn versus_credits_every_pop_to_its_player() {
+    // the reticle starts on the first ball
+    let mut app = mode_app(GameMode::Versus, vec![(0.0, 0.0), (-200.0, 0.0), (200.0, 0.0)], vec![]);
+    let tally = |app: &App, player| app.world().resource::<Tallies>().get(player);
+
+    press_key(&mut app, KeyCode::Space);
+    assert_eq!((tally(&app, Player::One), tally(&app, Player::Two)), (0, 1));
+
+    click(&mut app, Vec2::new(-200.0, 0.0));
+    assert_eq!((tally(&app, Player::One), tally(&app, Player::Two)), (1, 1));
+
+    app.world_mut().resource_mut::<ClickInput>().push(Click { pos: Vec2::new(200.0, 0.0), player: Player::Two });
+    app.update();
+    app.update();
+    assert_eq!(state(&app), AppState::Gameclear);
+    assert_eq!(app.world().resource::<Tallies>().winner(), Some(Player::Two));
+}
+
+/// A level of still balls at `positions`, with `kinds` mixed in.
+fn still_level(positions: Vec<(f32, f32)>, kinds: Vec<(BallKind, usize)>) -> Level {
+    Level {
+        name: "test".to_string(),
+        ball_count: positions.len(),
+        layout: Layout::Custom(positions),
+        speed: Spread::Fixed(0.0),
+        size: Spread::Fixed(30.0),
+        kinds,
+        ..Default::default()
+    }
+}
+
+/// A round of `mode` on `level`, started from the title screen.
+fn round_app(mode: GameMode, level: Level) -> App {
+    let mut app = build_app(SEED, FRAME_TIME);
+    app.insert_resource(mode);
+    app.insert_resource(CurrentLevel(level));
+    start_round(&mut app);
+    app.update();
+    app
+}
+
+/// A round of `mode` with still balls at `positions`, with `kinds` mixed in.
+fn mode_app(mode: GameMode, positions: Vec<(f32, f32)>, kinds: Vec<(BallKind, usize)>) -> App {
+    round_app(mode, still_level(positions, kinds))
+}
+
+/// `mode_app` in the classic mode.
+fn level_app(positions: Vec<(f32, f32)>, kinds: Vec<(BallKind, usize)>) -> App {
+    mode_app(GameMode::Classic, positions, kinds)
+}
+
+fn balls_of(app: &mut App, kind: BallKind) -> Vec<Vec2> {
+    app.world_mut()
+        .query::<(&Transform, &BallKind)>()
+        .iter(app.world())
+        .filter(|(_, ball_kind)| **ball_kind == kind)
+        .map(|(transform, _)| transform.translation.truncate())
+        .collect()
+}
+
+#[test]
+fn tough_balls_take_several_clicks() {
+    let mut app = level_app(vec![(0.0, 0.0), (200.0, 0.0)], vec![(BallKind::Tough(3), 1)]);
+    let tough = balls_of(&mut app, BallKind::Tough(3))[0];
+
+    click(&mut app, tough);
+    click(&mut app, tough);
+    assert_eq!(**app.world().resource::<BallCount>(), 2);
+    assert_eq!(app.world().resource::<Score>().points, 0);
+
+    click(&mut app, tough);
+    assert_eq!(**app.world().resource::<BallCount>(), 1);
+    assert!(balls_of(&mut app, BallKind::Tough(3)).is_empty());
+}
+
+#[test]
+fn pointer_clicks_land_inside_the_letterbox() {
+    let mut app = level_app(vec![(100.0, 50.0), (-200.0, 0.0)], vec![]);
+    // twice as wide as the play area, so the bars are 320 pixels on each side
+    let window = app.world_mut().spawn((
+        Window { resolution: (1280.0, 480.0).into(), ..Default::default() },
+        PrimaryWindow,
+    )).id();
+    app.world_mut().spawn(Camera2dBundle::default());
+    app
+        .insert_resource(Letterbox(true))
+        .init_resource::<Assets<Image>>()
+        .init_resource::<ManualTextureViews>()
+        .init_resource::<UiScale>()
+        .init_resource::<ButtonInput<MouseButton>>()
+        .add_event::<WindowCreated>()
+        .add_event::<WindowResized>()
+        .add_event::<WindowScaleFactorChanged>()
+        .add_event::<AssetEvent<Image>>()
+        .add_plugins(PlayAreaPlugin)
+        .add_systems(First, camera_system::<OrthographicProjection>);
+    app.world_mut().send_event(WindowResized { window, width: 1280.0, height: 480.0 });
+    app.update();
+    assert_eq!(app.world().resource::<PlayArea>().origin, Vec2::new(320.0, 0.0));
+
+    // the ball at (100, 50) is drawn 320 + 320 + 100 pixels from the left, 240 - 50 from the top
+    app.world_mut().get_mut::<Window>(window).unwrap().set_cursor_position(Some(Vec2::new(740.0, 190.0)));
+    app.world_mut().resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Left);
+    app.update();
+
+    assert_eq!(ball_positions(&mut app), vec![Vec2::new(-200.0, 0.0)]);
+}
+
+#[test]
+fn splitters_break_into_two_smaller_balls() {
+    let mut app = level_app(vec![(0.0, 0.0), (200.0, 0.0)], vec![(BallKind::Splitter, 1)]);
+    let splitter = balls_of(&mut app, BallKind::Splitter)[0];
+
+    click(&mut app, splitter);
+
+    assert_eq!(**app.world().resource::<BallCount>(), 3);
+    assert_eq!(ball_positions(&mut app).len(), 3);
+    let sizes: Vec<f32> = app.world_mut()
+        .query_filtered::<&Transform, With<Ball>>()
+        .iter(app.world())
+        .map(|transform| transform.scale.x)
+        .collect();
+    assert_eq!(sizes.iter().filter(|size| **size == 15.0).count(), 2);
+}
+
+#[test]
+fn bombs_pop_the_balls_around_them() {
+    let positions = vec![(0.0, 0.0), (40.0, 0.0), (0.0, 40.0), (200.0, 0.0)];
+    let mut app = level_app(positions, vec![(BallKind::Bomb(60.0), 1)]);
+    let bomb = balls_of(&mut app, BallKind::Bomb(60.0))[0];
+    assert_eq!(bomb, Vec2::ZERO);
+
+    click(&mut app, bomb);
+
+    assert_eq!(ball_positions(&mut app), vec![Vec2::new(200.0, 0.0)]);
+    assert_eq!(**app.world().resource::<BallCount>(), 1);
+}
+
+#[test]
+fn time_balls_change_the_time_left() {
+    let kinds = vec![(BallKind::BonusTime(5.0), 1), (BallKind::PenaltyTime(3.0), 1)];
+    let mut app = level_app(vec![(0.0, 0.0), (200.0, 0.0), (-200.0, 0.0)], kinds);
+    // the penalty ball is not needed to clear
+    assert_eq!(**app.world().resource::<BallCount>(), 2);
+    let remaining = |app: &App| app.world().resource::<GameTimer>().0.remaining_secs();
+    let before = remaining(&app);
+
+    let bonus = balls_of(&mut app, BallKind::BonusTime(5.0))[0];
+    click(&mut app, bonus);
+    assert!(remaining(&app) > before + 4.9);
+
+    let before = remaining(&app);
+    let penalty = balls_of(&mut app, BallKind::PenaltyTime(3.0))[0];
+    click(&mut app, penalty);
+    assert!(remaining(&app) < before - 2.9);
+    assert_eq!(**app.world().resource::<BallCount>(), 1);
+
+    let normal = balls_of(&mut app, BallKind::Normal)[0];
+    click(&mut app, normal);
+    app.update();
+    assert_eq!(state(&app), AppState::Gameclear);
+}
+
+/// Level `number` of the bundled sequence at `difficulty`, as the title screen selects it.
+fn bundled_level(number: usize, difficulty: Difficulty) -> Level {
+    #[derive(Deserialize)]
+    struct LevelSequenceFile {
+        levels: Vec<String>,
+    }
+
+    let sequence: LevelSequenceFile = ron::de::from_str(
+        include_str!("../../assets/levels/default.levels.ron")
+    ).unwrap();
+    let file = fs::read_to_string(format!("assets/{}", sequence.levels[number - 1])).unwrap();
+    difficulty.apply(&ron::de::from_str(&file).unwrap())
+}
+
+/// Runs the round until its results are in.
+fn run_to_results(app: &mut App) {
+    for _ in 0..MAX_FRAMES {
+        if matches!(state(app), AppState::Gameover | AppState::Gameclear) { break }
+        app.update();
+    }
+    app.update();
+}
+
+/// A classic round of the first level played with frames `frame_time`
+/// apart, popping a ball every few frames with some misses in between.
+fn scripted_round(frame_time: f64) -> Replay {
+    let mut app = build_app(SEED, frame_time);
+    app.insert_resource(CurrentLevel(bundled_level(1, Difficulty::Normal)));
+    start_round(&mut app);
+
+    for frame in 0..MAX_FRAMES {
+        if state(&app) != AppState::Ingame { break }
+        match frame % 15 {
+            0 => {
+                // near the edge, where the drawn ball is off the stepped one
+                let pos = ball_positions(&mut app)[0] + Vec2::X * (15.0 + CURSOR_RANGE - 2.0);
+                app.world_mut().resource_mut::<ClickInput>().push(pos.into());
+            },
+            7 if frame % 60 == 7 => app.world_mut().resource_mut::<ClickInput>().push(Vec2::new(300.0, 220.0).into()),
+            _ => {},
+        }
+        app.update();
+    }
+    run_to_results(&mut app);
+    (**app.world().resource::<Recording>()).clone()
+}
+
+/// Plays `replay` back headless with frames `frame_time` apart, returning
+/// the recording of the played back round.
+fn play_back(replay: &Replay, frame_time: f64) -> Replay {
+    let mut app = build_app(replay.seed, frame_time);
+    app.insert_resource(replay.mode);
+    app.insert_resource(replay.difficulty);
+    app.insert_resource(CurrentLevel(bundled_level(replay.level, replay.difficulty)));
+    // no window to fit, so the play area is the recorded one whatever its size
+    app.insert_resource(PlayArea::fit(replay.play_area, false));
+    // loaded on the title screen a frame before the round starts, after
+    // entering it stopped any earlier playback
+    app.update();
+    app.insert_resource(Playback::new(replay.clone()));
+    start_round(&mut app);
+    run_to_results(&mut app);
+    (**app.world().resource::<Recording>()).clone()
+}
+
+#[test]
+fn replay_plays_back_the_recorded_round_at_any_frame_rate() {
+    // several frames to a step, and several steps to a frame
+    for frame_time in [FIXED_TIME, FIXED_TIME / 2.0, FIXED_TIME * 3.0] {
+        let replay = scripted_round(frame_time);
+
+        assert!(replay.result.unwrap().points > 0, "{}", frame_time);
+        assert!(replay.clicks.len() > BALL_COUNT, "{}", frame_time);
+        assert!(replay.clicks.windows(2).all(|pair| pair[0].tick <= pair[1].tick));
+
+        for playback_frame_time in [FIXED_TIME, FIXED_TIME / 2.0, FIXED_TIME * 3.0] {
+            assert_eq!(play_back(&replay, playback_frame_time), replay, "{} {}", frame_time, playback_frame_time);
+        }
+    }
+}
+
+#[test]
+fn replay_fixtures_still_play_out_the_same() {
+    let mut paths: Vec<_> = fs::read_dir("replays").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let replay: Replay = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(play_back(&replay, FIXED_TIME), replay, "{}", path.display());
+    }
+}
+
+/// Rewrites the fixture of the scripted round after a change meant to alter
+/// how rounds play out: `cargo test -- --ignored record_replay_fixture`.
+#[test]
+#[ignore]
+fn record_replay_fixture() {
+    let json = serde_json::to_string_pretty(&scripted_round(FIXED_TIME)).unwrap();
+    fs::write("replays/classic.replay.json", json).unwrap();
+}
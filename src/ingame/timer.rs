@@ -1,36 +1,55 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 
+use super::{PhysicsSet, RestartRound};
 use crate::{
     AppState,
+    Round,
+    GameMode,
     GameTimer,
+    level::CurrentLevel,
 };
 
+// endless and time-attack rounds have no time limit, the timer only measures
+// the time played
+const UNLIMITED_DURATION: Duration = Duration::from_secs(u32::MAX as u64);
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut timer: ResMut<GameTimer>,
+    level: Res<CurrentLevel>,
+    mode: Res<GameMode>,
+) {
+    timer.0 = if mode.has_time_limit() {
+        println!("timer: setup with {} seconds", level.time_limit);
+        Timer::from_seconds(level.time_limit, TimerMode::Once)
+    } else {
+        println!("timer: setup without time limit");
+        Timer::new(UNLIMITED_DURATION, TimerMode::Once)
+    };
+}
+
+/// Ticked by fixed steps like the balls, so a replay runs out of time on the same step.
 fn update(
     mut timer: ResMut<GameTimer>,
     mut next_state: ResMut<NextState<AppState>>,
     time: Res<Time>,
 ) {
-    if timer.0.tick(time.delta()).just_finished() {
+    // penalty balls may have ticked the timer past its end
+    if timer.0.tick(time.delta()).finished() {
         println!("timer: moved state to Gameover from Ingame");
         next_state.set(AppState::Gameover);
     }
 }
 
-fn reset(
-    mut timer: ResMut<GameTimer>,
-) {
-    println!("timer: reset");
-    timer.0.reset();
-}
-
 pub struct TimerPlugin;
 
 impl Plugin for TimerPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(Update, update.run_if(in_state(AppState::Ingame)))
-            .add_systems(OnExit(AppState::Gameover), reset)
-            .add_systems(OnExit(AppState::Gameclear), reset)
+            .observe(reset)
+            .add_systems(FixedUpdate, update.after(PhysicsSet).run_if(in_state(Round::Playing)))
         ;
     }
 }
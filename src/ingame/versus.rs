@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+
+use super::{
+    ClickSet,
+    RestartRound,
+    balls::BallPopped,
+};
+use crate::{
+    Round,
+    GameMode,
+    Player,
+};
+
+const WIN_TEXT: &str = "のかち!";
+const DRAW_TEXT: &str = "ひきわけ";
+
+/// Balls each player popped in a two-player round, counted like `BallCount`.
+///
+/// Exists from the start of a two-player round until the next round starts,
+/// so the results screens can show the winner.
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct Tallies([usize; 2]);
+
+impl Tallies {
+    pub fn get(&self, player: Player) -> usize {
+        self.0[player.index()]
+    }
+
+    /// Player with the most pops, `None` on a draw.
+    pub fn winner(&self) -> Option<Player> {
+        let [one, two] = self.0;
+        match one.cmp(&two) {
+            Ordering::Greater => Some(Player::One),
+            Ordering::Less => Some(Player::Two),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// The winner for the results screens, or a draw.
+    pub fn winner_text(&self) -> String {
+        match self.winner() {
+            Some(player) => format!("{}{}", player.label(), WIN_TEXT),
+            None => DRAW_TEXT.to_string(),
+        }
+    }
+
+    /// Pops of both players, like `1P: 3 - 2P: 5`.
+    pub fn text(&self) -> String {
+        Player::ALL.map(|player| format!("{}: {}", player.label(), self.get(player))).join(" - ")
+    }
+}
+
+fn reset(
+    _trigger: Trigger<RestartRound>,
+    mut commands: Commands,
+    mode: Res<GameMode>,
+) {
+    if *mode != GameMode::Versus {
+        commands.remove_resource::<Tallies>();
+        return
+    }
+    commands.insert_resource(Tallies::default());
+}
+
+fn tally(
+    mut tallies: ResMut<Tallies>,
+    mut popped_events: EventReader<BallPopped>,
+) {
+    for popped in popped_events.read() {
+        if !popped.kind.counts() { continue }
+        tallies.0[popped.player.index()] += 1;
+        println!("versus: {} popped, {}", popped.player.label(), tallies.get(popped.player));
+    }
+}
+
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .observe(reset)
+            .add_systems(Update, tally
+                .after(ClickSet::Handle)
+                .run_if(in_state(Round::Playing))
+                .run_if(resource_exists::<Tallies>))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+    use crate::{AppState, level::BallKind};
+
+    fn versus_app(mode: GameMode) -> App {
+        let mut app = App::new();
+        app
+            .add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_state(AppState::Ingame)
+            .add_sub_state::<Round>()
+            .add_event::<BallPopped>()
+            .insert_resource(mode)
+            .add_plugins(VersusPlugin);
+        // the observer is registered in the first update
+        app.update();
+        app.world_mut().trigger(RestartRound);
+        app.update();
+        app
+    }
+
+    fn pop(app: &mut App, kind: BallKind, player: Player) {
+        app.world_mut().send_event(BallPopped { speed: 0.0, size: 30.0, kind, player });
+        app.update();
+    }
+
+    #[test]
+    fn pops_that_count_go_to_the_player_who_made_them() {
+        let mut app = versus_app(GameMode::Versus);
+
+        pop(&mut app, BallKind::Normal, Player::Two);
+        pop(&mut app, BallKind::Bomb(60.0), Player::One);
+        pop(&mut app, BallKind::Normal, Player::Two);
+        pop(&mut app, BallKind::PenaltyTime(3.0), Player::One);
+
+        assert_eq!(*app.world().resource::<Tallies>(), Tallies([1, 2]));
+    }
+
+    #[test]
+    fn other_modes_keep_no_tallies() {
+        let mut app = versus_app(GameMode::Versus);
+        app.insert_resource(GameMode::Classic);
+        app.world_mut().trigger(RestartRound);
+        app.update();
+
+        assert!(app.world().get_resource::<Tallies>().is_none());
+    }
+
+    #[test]
+    fn most_pops_win() {
+        assert_eq!(Tallies([3, 5]).winner(), Some(Player::Two));
+        assert_eq!(Tallies([4, 1]).winner(), Some(Player::One));
+        assert_eq!(Tallies([2, 2]).winner(), None);
+        assert_eq!(Tallies([3, 5]).get(Player::One), 3);
+    }
+
+    #[test]
+    fn texts_name_the_winner_and_both_tallies() {
+        assert_eq!(Tallies([3, 5]).winner_text(), "2Pのかち!");
+        assert_eq!(Tallies([2, 2]).winner_text(), "ひきわけ");
+        assert_eq!(Tallies([3, 5]).text(), "1P: 3 - 2P: 5");
+    }
+}
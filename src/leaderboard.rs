@@ -0,0 +1,317 @@
+use bevy::prelude::*;
+
+use crate::{
+    PATH_FONT,
+    AppState,
+    GameMode,
+    actions::{Action, ActionInput, ActionMap},
+    difficulty::Difficulty,
+    highscore::{HighScores, Table},
+    level::{CurrentLevel, LevelNames},
+    playarea::PlayArea,
+};
+
+const LEADERBOARD_TEXT: &str = "ランキング";
+const LEADERBOARD_SIZE: f32 = 32.0;
+const CLEARTIME_TEXT: &str = "クリアタイム";
+const REMAINING_TEXT: &str = "のこりボール";
+const SURVIVAL_TEXT: &str = "いきのこったじかん";
+const MODE_TEXT: &str = "モード";
+const LEVEL_TEXT: &str = "レベル";
+const DIFFICULTY_TEXT: &str = "むずかしさ";
+const EMPTY_TEXT: &str = "-";
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const HEADER_COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+const TEXT_SIZE: f32 = 20.0;
+const TEXT_PADDING: f32 = 50.0;
+const COLUMN_WIDTH: f32 = 240.0;
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+
+#[derive(Component)]
+struct LeaderboardScreen;
+
+/// Mode, level and difficulty of the shown page, each tappable on touch
+/// screens to go to the next one, and the back to title line.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum LeaderboardButton {
+    Mode,
+    Level,
+    Difficulty,
+    BackToTitle,
+}
+
+/// Column of the leaderboard, showing the table of the shown mode at its index.
+#[derive(Component, Deref)]
+struct TableText(usize);
+
+/// Game mode, level and difficulty whose leaderboard is shown.
+#[derive(Resource)]
+struct ShownPage {
+    mode: GameMode,
+    level: String,
+    difficulty: Difficulty,
+}
+
+/// Game modes that keep records, in the order the leaderboard pages through them.
+fn ranked_modes() -> Vec<GameMode> {
+    GameMode::ALL.into_iter().filter(|mode| !Table::of(*mode).is_empty()).collect()
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+    action_map: Res<ActionMap>,
+    (mode, level, difficulty): (Res<GameMode>, Res<CurrentLevel>, Res<Difficulty>),
+) {
+    println!("leaderboard: setup");
+    let mode = if ranked_modes().contains(&mode) { *mode } else { GameMode::default() };
+    commands.insert_resource(ShownPage { mode, level: level.name.clone(), difficulty: *difficulty });
+    // leaderboard
+    commands.spawn((
+        TextBundle::from_section(
+            LEADERBOARD_TEXT,
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: LEADERBOARD_SIZE,
+                color: TEXT_COLOR,
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top: Val::Px(TEXT_PADDING * 0.5),
+            ..Default::default()
+        }),
+        LeaderboardScreen,
+    ))
+    .insert(Name::new("leaderboard"));
+    // page
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Relative,
+                justify_self: JustifySelf::Center,
+                top: Val::Px(TEXT_PADDING * 1.5),
+                height: Val::Px(TEXT_SIZE * 1.25),
+                column_gap: Val::Px(TEXT_SIZE),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        LeaderboardScreen,
+    ))
+    .insert(Name::new("page"))
+    .with_children(|parent| {
+        for button in [LeaderboardButton::Mode, LeaderboardButton::Level, LeaderboardButton::Difficulty] {
+            parent.spawn((
+                TextBundle::from_section(
+                    String::new(),
+                    TextStyle {
+                        font: asset_server.load(PATH_FONT),
+                        font_size: TEXT_SIZE,
+                        color: TEXT_COLOR,
+                    }
+                )
+                .with_background_color(BUTTON_COLOR),
+                Interaction::default(),
+                button,
+            ))
+            .insert(Name::new("pagebutton"));
+        }
+    });
+    // tables
+    let columns = [play_area.size.x / 4.0, play_area.size.x / 4.0 * 3.0];
+
+    for (column, center) in columns.into_iter().enumerate() {
+        commands.spawn((
+            TextBundle::from_sections([
+                TextSection::from_style(
+                    TextStyle {
+                        font: asset_server.load(PATH_FONT),
+                        font_size: TEXT_SIZE,
+                        color: HEADER_COLOR,
+                    },
+                ),
+                TextSection::from_style(
+                    TextStyle {
+                        font: asset_server.load(PATH_FONT),
+                        font_size: TEXT_SIZE,
+                        color: TEXT_COLOR,
+                    },
+                ),
+            ])
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(TEXT_PADDING * 2.5),
+                left: Val::Px(center - COLUMN_WIDTH / 2.0),
+                width: Val::Px(COLUMN_WIDTH),
+                ..Default::default()
+            }),
+            TableText(column),
+            LeaderboardScreen,
+        ))
+        .insert(Name::new("table"));
+    }
+    // change page
+    let hint = |previous, next| format!("{} {}", action_map.hint(previous), action_map.hint(next));
+    let hints = [
+        (format!("{}: {}", MODE_TEXT, hint(Action::PreviousMode, Action::NextMode)), play_area.size.y - TEXT_PADDING * 2.5),
+        (format!("{}: {} / {}: {}", DIFFICULTY_TEXT, hint(Action::PreviousDifficulty, Action::NextDifficulty), LEVEL_TEXT, action_map.hint(Action::NextLevel)), play_area.size.y - TEXT_PADDING * 2.0),
+    ];
+
+    for (hint, top) in hints {
+        commands.spawn((
+            TextBundle::from_section(
+                hint,
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: TEXT_SIZE,
+                    color: TEXT_COLOR,
+                }
+            )
+            .with_style(Style {
+                position_type: PositionType::Relative,
+                justify_self: JustifySelf::Center,
+                top: Val::Px(top),
+                ..Default::default()
+            }),
+            LeaderboardScreen,
+        ))
+        .insert(Name::new("hint"));
+    }
+    // back to title
+    commands.spawn((
+        TextBundle::from_section(
+            format!("{}: {}", Action::BackToTitle.label(), action_map.hint(Action::BackToTitle)),
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: TEXT_SIZE,
+                color: TEXT_COLOR,
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top: Val::Px(play_area.size.y - TEXT_PADDING * 1.5),
+            ..Default::default()
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        LeaderboardButton::BackToTitle,
+        LeaderboardScreen,
+    ))
+    .insert(Name::new("backtotitle"));
+}
+
+fn update(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut page: ResMut<ShownPage>,
+    button_query: Query<(&Interaction, &LeaderboardButton), Changed<Interaction>>,
+    (actions, level_names): (ActionInput, LevelNames),
+) {
+    let tapped = |button| button_query.iter()
+        .any(|(interaction, pressed)| *interaction == Interaction::Pressed && *pressed == button);
+
+    if actions.just_pressed(Action::BackToTitle) || tapped(LeaderboardButton::BackToTitle) {
+        println!("leaderboard: moved state to Mainmenu from Leaderboard");
+        next_state.set(AppState::Mainmenu);
+    }
+
+    if actions.just_pressed(Action::NextLevel) || tapped(LeaderboardButton::Level) {
+        let names = level_names.get();
+        let index = names.iter().position(|name| *name == page.level);
+        // before the levels are loaded the shown level stays
+        if let Some(name) = index.map_or(names.first(), |index| names.get((index + 1) % names.len())) {
+            page.level = name.clone();
+        }
+    }
+
+    let modes = ranked_modes();
+    let index = modes.iter().position(|mode| *mode == page.mode).unwrap_or(0);
+    let len = modes.len();
+    let difficulty = Difficulty::ALL.iter().position(|preset| *preset == page.difficulty).unwrap_or(0);
+    let difficulties = Difficulty::ALL.len();
+
+    if actions.just_pressed(Action::PreviousMode) {
+        page.mode = modes[(index + len - 1) % len];
+    } else if actions.just_pressed(Action::NextMode) || tapped(LeaderboardButton::Mode) {
+        page.mode = modes[(index + 1) % len];
+    }
+    if actions.just_pressed(Action::PreviousDifficulty) {
+        page.difficulty = Difficulty::ALL[(difficulty + difficulties - 1) % difficulties];
+    } else if actions.just_pressed(Action::NextDifficulty) || tapped(LeaderboardButton::Difficulty) {
+        page.difficulty = Difficulty::ALL[(difficulty + 1) % difficulties];
+    }
+}
+
+fn update_tables(
+    mut page_query: Query<(&mut Text, &LeaderboardButton), Without<TableText>>,
+    mut table_query: Query<(&mut Text, &TableText)>,
+    page: Res<ShownPage>,
+    highscores: Res<HighScores>,
+) {
+    if !page.is_changed() && !highscores.is_changed() { return }
+
+    let leaderboard = highscores.leaderboard(page.mode, &page.level, page.difficulty);
+
+    for (mut text, button) in page_query.iter_mut() {
+        let label = match button {
+            LeaderboardButton::Mode => page.mode.label(),
+            LeaderboardButton::Level => &page.level,
+            LeaderboardButton::Difficulty => page.difficulty.label(),
+            LeaderboardButton::BackToTitle => continue,
+        };
+        text.sections[0].value = format!("< {} >", label);
+    }
+    for (mut text, column) in table_query.iter_mut() {
+        // modes with fewer tables leave the other columns empty
+        let Some(&table) = Table::of(page.mode).get(**column) else {
+            for section in text.sections.iter_mut() { section.value.clear() }
+            continue
+        };
+        let rows: Vec<String> = leaderboard.records(table)
+            .iter()
+            .enumerate()
+            .map(|(rank, record)| match table {
+                Table::ClearTime | Table::SurvivalTime => format!("{}. {} {:.2}", rank + 1, record.name, record.value),
+                Table::RemainingBalls => format!("{}. {} {}", rank + 1, record.name, record.value),
+            })
+            .collect();
+
+        text.sections[0].value = format!("{}\n", header(table));
+        text.sections[1].value = if rows.is_empty() { EMPTY_TEXT.to_string() } else { rows.join("\n") };
+    }
+}
+
+fn header(table: Table) -> &'static str {
+    match table {
+        Table::ClearTime => CLEARTIME_TEXT,
+        Table::RemainingBalls => REMAINING_TEXT,
+        Table::SurvivalTime => SURVIVAL_TEXT,
+    }
+}
+
+fn despawn(
+    mut commands: Commands,
+    query: Query<Entity, With<LeaderboardScreen>>,
+) {
+    println!("leaderboard: despawn");
+    commands.remove_resource::<ShownPage>();
+    for entity in query.iter() { commands.entity(entity).despawn_recursive() }
+}
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Leaderboard), setup)
+            .add_systems(Update, (
+                update,
+                update_tables,
+            ).chain().run_if(in_state(AppState::Leaderboard)))
+            .add_systems(OnExit(AppState::Leaderboard), despawn)
+        ;
+    }
+}
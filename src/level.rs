@@ -0,0 +1,526 @@
+use std::fmt;
+
+use bevy::{
+    prelude::*,
+    asset::{
+        io::Reader,
+        AssetLoader,
+        AsyncReadExt,
+        LoadContext,
+    },
+    ecs::system::SystemParam,
+};
+use rand::{seq::SliceRandom, Rng};
+use serde::Deserialize;
+
+use crate::{
+    BACKGROUND_COLOR,
+    BALL_COUNT,
+    GAMETIME_LIMIT,
+    AppState,
+    difficulty::Difficulty,
+};
+
+const PATH_LEVELS: &str = "levels/default.levels.ron";
+const BALL_SIZE: f32 = 30.0;
+const BALL_SPEED: f32 = 240.0;
+
+/// Where the balls of a level start.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum Layout {
+    /// Square spiral around the center, one ball size apart.
+    Spiral,
+    /// Evenly spaced on a circle.
+    Ring(f32),
+    /// Explicit positions, one per ball.
+    Custom(Vec<(f32, f32)>),
+}
+
+impl Layout {
+    /// Start positions of `count` balls of diameter `size`, with the spawn index as z.
+    pub fn positions(&self, count: usize, size: f32) -> Vec<Vec3> {
+        let positions: Vec<Vec2> = match self {
+            Layout::Spiral => spiral(count).into_iter().map(|pos| pos * size).collect(),
+            Layout::Ring(radius) => (0..count)
+                .map(|i| Vec2::from_angle(std::f32::consts::TAU * i as f32 / count as f32) * *radius)
+                .collect(),
+            Layout::Custom(positions) => positions.iter().map(|(x, y)| Vec2::new(*x, *y)).collect(),
+        };
+
+        positions
+            .into_iter()
+            .take(count)
+            .enumerate()
+            .map(|(i, pos)| pos.extend(i as f32))
+            .collect()
+    }
+}
+
+/// A value of every ball, the same for all or drawn between two bounds.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum Spread {
+    Fixed(f32),
+    Range(f32, f32),
+}
+
+impl Spread {
+    pub fn max(self) -> f32 {
+        match self {
+            Spread::Fixed(value) => value,
+            Spread::Range(min, max) => min.max(max),
+        }
+    }
+
+    pub fn scale(self, factor: f32) -> Spread {
+        match self {
+            Spread::Fixed(value) => Spread::Fixed(value * factor),
+            Spread::Range(min, max) => Spread::Range(min * factor, max * factor),
+        }
+    }
+
+    /// Value of one ball; a fixed value draws nothing from `rng`.
+    pub fn sample(self, rng: &mut impl Rng) -> f32 {
+        match self {
+            Spread::Fixed(value) => value,
+            Spread::Range(min, max) if min < max => rng.gen_range(min..=max),
+            Spread::Range(min, _) => min,
+        }
+    }
+}
+
+/// What popping a ball does besides removing it.
+#[derive(Component, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum BallKind {
+    Normal,
+    /// Pops after this many clicks.
+    Tough(u32),
+    /// Breaks into two balls of half the size.
+    Splitter,
+    /// Pops every ball within this many pixels as well.
+    Bomb(f32),
+    /// Adds this many seconds to the time limit.
+    BonusTime(f32),
+    /// Takes this many seconds off the time limit when clicked. Not needed
+    /// to clear the round.
+    PenaltyTime(f32),
+}
+
+impl BallKind {
+    /// Whether the round is cleared only once this ball is popped.
+    pub fn counts(self) -> bool {
+        !matches!(self, BallKind::PenaltyTime(_))
+    }
+}
+
+/// Cells of a square spiral: up, right, down, left with growing sides.
+fn spiral(count: usize) -> Vec<Vec2> {
+    let directions = [Vec2::Y, Vec2::X, Vec2::NEG_Y, Vec2::NEG_X];
+    let mut positions = vec![Vec2::ZERO];
+    let mut pos = Vec2::ZERO;
+    let mut turn = 0;
+
+    while positions.len() < count {
+        let direction = directions[turn % directions.len()];
+        let side = turn / 2 + 1;
+
+        for _ in 0..side {
+            pos += direction;
+            positions.push(pos);
+        }
+        turn += 1;
+    }
+
+    positions.truncate(count);
+    positions
+}
+
+/// One round: how many balls, where, how fast and how big, and how long to pop them.
+#[derive(Asset, TypePath, Deserialize, Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub ball_count: usize,
+    pub layout: Layout,
+    /// Highest speed of a ball along each axis in pixels per second.
+    pub speed: Spread,
+    /// Diameter of a ball in pixels.
+    pub size: Spread,
+    pub time_limit: f32,
+    /// Background as sRGB.
+    pub background: (f32, f32, f32),
+    /// How many balls of each special kind, the rest being normal.
+    #[serde(default)]
+    pub kinds: Vec<(BallKind, usize)>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        let background = BACKGROUND_COLOR.to_srgba();
+
+        Self {
+            name: String::new(),
+            ball_count: BALL_COUNT,
+            layout: Layout::Spiral,
+            speed: Spread::Fixed(BALL_SPEED),
+            size: Spread::Fixed(BALL_SIZE),
+            time_limit: GAMETIME_LIMIT,
+            background: (background.red, background.green, background.blue),
+            kinds: Vec::new(),
+        }
+    }
+}
+
+impl Level {
+    pub fn background_color(&self) -> Color {
+        let (red, green, blue) = self.background;
+        Color::srgb(red, green, blue)
+    }
+
+    /// Kinds of `count` balls in spawn order, special ones shuffled among
+    /// the normal ones.
+    pub fn ball_kinds(&self, count: usize, rng: &mut impl Rng) -> Vec<BallKind> {
+        let mut kinds: Vec<BallKind> = self.kinds
+            .iter()
+            .flat_map(|(kind, count)| std::iter::repeat_n(*kind, *count))
+            .take(count)
+            .collect();
+        // levels without special balls keep the random sequence of the classic game
+        if kinds.is_empty() { return vec![BallKind::Normal; count] }
+
+        kinds.resize(count, BallKind::Normal);
+        kinds.shuffle(rng);
+        kinds
+    }
+}
+
+/// Levels played one after another, each cleared round advancing to the next.
+#[derive(Asset, TypePath, Debug)]
+pub struct LevelSequence {
+    pub levels: Vec<Handle<Level>>,
+}
+
+#[derive(Deserialize)]
+struct LevelSequenceFile {
+    levels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum LevelLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for LevelLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelLoaderError::Io(err) => write!(f, "could not read level: {}", err),
+            LevelLoaderError::Ron(err) => write!(f, "could not parse level: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LevelLoaderError {}
+
+impl From<std::io::Error> for LevelLoaderError {
+    fn from(err: std::io::Error) -> Self {
+        LevelLoaderError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for LevelLoaderError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        LevelLoaderError::Ron(err)
+    }
+}
+
+#[derive(Default)]
+struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Level, LevelLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[derive(Default)]
+struct LevelSequenceLoader;
+
+impl AssetLoader for LevelSequenceLoader {
+    type Asset = LevelSequence;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LevelSequence, LevelLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: LevelSequenceFile = ron::de::from_bytes(&bytes)?;
+
+        Ok(LevelSequence {
+            levels: file.levels.into_iter().map(|path| load_context.load(path)).collect(),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["levels.ron"]
+    }
+}
+
+/// Level of the current round with the difficulty applied, the built-in
+/// default until the sequence is loaded.
+#[derive(Resource, Deref, DerefMut, Default, Debug)]
+pub struct CurrentLevel(pub Level);
+
+/// Position in the level sequence.
+#[derive(Resource, Default, Debug)]
+pub struct LevelProgress {
+    index: usize,
+    len: usize,
+}
+
+impl LevelProgress {
+    pub fn number(&self) -> usize {
+        self.index + 1
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.index + 1 < self.len
+    }
+
+    pub fn advance(&mut self) {
+        if self.has_next() { self.index += 1 }
+    }
+
+    /// Jumps to level `number`, counted from 1 like `number()`.
+    pub fn select(&mut self, number: usize) {
+        self.index = number.saturating_sub(1);
+    }
+}
+
+#[derive(Resource, Deref)]
+struct LevelSequenceHandle(Handle<LevelSequence>);
+
+/// Names of the levels in the sequence, in playing order.
+#[derive(SystemParam)]
+pub struct LevelNames<'w> {
+    handle: Option<Res<'w, LevelSequenceHandle>>,
+    sequences: Res<'w, Assets<LevelSequence>>,
+    levels: Res<'w, Assets<Level>>,
+}
+
+impl LevelNames<'_> {
+    /// Empty until the sequence and its levels are loaded.
+    pub fn get(&self) -> Vec<String> {
+        let Some(sequence) = self.handle.as_ref().and_then(|handle| self.sequences.get(&***handle)) else { return Vec::new() };
+
+        sequence.levels
+            .iter()
+            .filter_map(|level| self.levels.get(level))
+            .map(|level| level.name.clone())
+            .collect()
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    println!("level: setup");
+    commands.insert_resource(LevelSequenceHandle(asset_server.load(PATH_LEVELS)));
+}
+
+fn select_level(
+    mut current_level: ResMut<CurrentLevel>,
+    mut progress: ResMut<LevelProgress>,
+    mut sequence_events: EventReader<AssetEvent<LevelSequence>>,
+    mut level_events: EventReader<AssetEvent<Level>>,
+    handle: Res<LevelSequenceHandle>,
+    sequences: Res<Assets<LevelSequence>>,
+    (levels, difficulty): (Res<Assets<Level>>, Res<Difficulty>),
+) {
+    let loaded = sequence_events.read().count() + level_events.read().count() > 0;
+    if !loaded && !progress.is_changed() && !difficulty.is_changed() { return }
+
+    let Some(sequence) = sequences.get(&**handle) else { return };
+    if progress.len != sequence.levels.len() { progress.len = sequence.levels.len() }
+
+    let Some(level) = sequence.levels.get(progress.index).and_then(|level| levels.get(level)) else { return };
+    let level = difficulty.apply(level);
+    if **current_level == level { return }
+
+    println!("level: selected level {} {} at {:?}", progress.number(), level.name, *difficulty);
+    **current_level = level;
+}
+
+fn reset_progress(
+    mut progress: ResMut<LevelProgress>,
+) {
+    println!("level: reset progress");
+    progress.index = 0;
+}
+
+fn apply_background(
+    mut clear_color: ResMut<ClearColor>,
+    current_level: Res<CurrentLevel>,
+) {
+    clear_color.0 = current_level.background_color();
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<Level>()
+            .init_asset::<LevelSequence>()
+            .init_asset_loader::<LevelLoader>()
+            .init_asset_loader::<LevelSequenceLoader>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<LevelProgress>()
+            .init_resource::<Difficulty>()
+            .add_systems(Startup, setup)
+            .add_systems(PostUpdate, select_level)
+            .add_systems(OnEnter(AppState::Mainmenu), reset_progress)
+            .add_systems(OnEnter(AppState::Ingame), apply_background)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn spiral_matches_the_classic_layout() {
+        let cells: Vec<(f32, f32)> = spiral(22).into_iter().map(|pos| (pos.x, pos.y)).collect();
+
+        assert_eq!(cells, vec![
+            ( 0.0,  0.0), ( 0.0,  1.0), ( 1.0,  1.0), ( 1.0,  0.0), ( 1.0, -1.0),
+            ( 0.0, -1.0), (-1.0, -1.0), (-1.0,  0.0), (-1.0,  1.0), (-1.0,  2.0),
+            ( 0.0,  2.0), ( 1.0,  2.0), ( 2.0,  2.0), ( 2.0,  1.0), ( 2.0,  0.0),
+            ( 2.0, -1.0), ( 2.0, -2.0), ( 1.0, -2.0), ( 0.0, -2.0), (-1.0, -2.0),
+            (-2.0, -2.0), (-2.0, -1.0),
+        ]);
+    }
+
+    #[test]
+    fn layouts_place_one_ball_per_position() {
+        let ring = Layout::Ring(100.0).positions(8, 30.0);
+        assert_eq!(ring.len(), 8);
+        assert!(ring.iter().all(|pos| (pos.truncate().length() - 100.0).abs() < 0.01));
+
+        let custom = Layout::Custom(vec![(1.0, 2.0), (3.0, 4.0)]).positions(5, 30.0);
+        assert_eq!(custom, vec![Vec3::new(1.0, 2.0, 0.0), Vec3::new(3.0, 4.0, 1.0)]);
+
+        let spiral = Layout::Spiral.positions(3, 30.0);
+        assert_eq!(spiral[2], Vec3::new(30.0, 30.0, 2.0));
+    }
+
+    #[test]
+    fn level_files_parse() {
+        let level: Level = ron::de::from_str(r#"(
+            name: "test",
+            ball_count: 3,
+            layout: Custom([(0.0, 0.0), (40.0, 0.0), (80.0, 0.0)]),
+            speed: 100.0,
+            size: 20.0,
+            time_limit: 10.0,
+            background: (0.0, 0.0, 0.0),
+        )"#).unwrap();
+
+        assert_eq!(level.ball_count, 3);
+        assert_eq!(level.layout.positions(level.ball_count, level.size.max()).len(), 3);
+    }
+
+    #[test]
+    fn bundled_levels_parse() {
+        let sequence: LevelSequenceFile = ron::de::from_str(
+            include_str!("../assets/levels/default.levels.ron")
+        ).unwrap();
+
+        for path in sequence.levels {
+            let file = std::fs::read_to_string(format!("assets/{}", path)).unwrap();
+            let level: Level = ron::de::from_str(&file).unwrap();
+            assert!(level.layout.positions(level.ball_count, level.size.max()).len() >= level.ball_count);
+        }
+    }
+
+    #[test]
+    fn spreads_are_fixed_or_ranges() {
+        let level: Level = ron::de::from_str(r#"(
+            name: "test",
+            ball_count: 3,
+            layout: Spiral,
+            speed: (100.0, 300.0),
+            size: 20.0,
+            time_limit: 10.0,
+            background: (0.0, 0.0, 0.0),
+        )"#).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+
+        assert_eq!(level.size, Spread::Fixed(20.0));
+        assert_eq!(level.speed, Spread::Range(100.0, 300.0));
+        assert_eq!(level.speed.max(), 300.0);
+        for _ in 0..100 {
+            assert!((100.0..=300.0).contains(&level.speed.sample(&mut rng)));
+        }
+        assert_eq!(Spread::Range(5.0, 5.0).sample(&mut rng), 5.0);
+    }
+
+    #[test]
+    fn special_balls_are_mixed_into_the_normal_ones() {
+        let level: Level = ron::de::from_str(r#"(
+            name: "test",
+            ball_count: 10,
+            layout: Spiral,
+            speed: 100.0,
+            size: 20.0,
+            time_limit: 10.0,
+            background: (0.0, 0.0, 0.0),
+            kinds: [(Tough(3), 2), (Bomb(60.0), 1), (PenaltyTime(2.0), 1)],
+        )"#).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let kinds = level.ball_kinds(level.ball_count, &mut rng);
+        let count = |kind| kinds.iter().filter(|k| **k == kind).count();
+
+        assert_eq!(kinds.len(), 10);
+        assert_eq!(count(BallKind::Tough(3)), 2);
+        assert_eq!(count(BallKind::Bomb(60.0)), 1);
+        assert_eq!(count(BallKind::Normal), 6);
+        assert_eq!(kinds.iter().filter(|kind| kind.counts()).count(), 9);
+        assert_eq!(Level::default().ball_kinds(3, &mut rng), vec![BallKind::Normal; 3]);
+    }
+
+    #[test]
+    fn progress_stops_at_the_last_level() {
+        let mut progress = LevelProgress { index: 0, len: 2 };
+
+        assert!(progress.has_next());
+        progress.advance();
+        assert_eq!(progress.number(), 2);
+        assert!(!progress.has_next());
+        progress.advance();
+        assert_eq!(progress.number(), 2);
+    }
+}
@@ -2,16 +2,33 @@ use bevy::{
     prelude::*,
     asset::AssetMetaCheck,
 };
+use rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+use serde::{Deserialize, Serialize};
 
+mod actions;
+mod difficulty;
 mod mainmenu;
 mod ingame;
 mod gameover;
 mod gameclear;
+mod gamepad;
+mod highscore;
+mod leaderboard;
+mod level;
+mod playarea;
+mod replay;
+mod settings;
+mod storage;
 
 const GAMETITLE: &str = "いっとくクリックゲーム";
 const WINDOW_SIZE: Vec2 = Vec2::new(640.0, 480.0);
 const BACKGROUND_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
-const CURSOR_RANGE: f32 = 10.0;
+// how far outside a ball or button a click still hits at normal difficulty
+const CURSOR_RANGE: f32 = 5.0;
 const BALL_COUNT: usize = 20;
 const GAMETIME_LIMIT: f32 = 25.0;
 const PATH_FONT: &str = "fonts/misaki_gothic.ttf";
@@ -20,37 +37,222 @@ const PATH_IMAGE_PAUSEBUTTON: &str = "images/pausebutton-dark.png";
 const PATH_SOUND_BGM: &str = "ittoku-click-game/bgm.ogg";
 const PATH_SOUND_CLICK: &str = "sounds/click.ogg";
 const PATH_SOUND_DESPAWN: &str = "sounds/despawn.ogg";
+const SEED_ARG: &str = "--seed";
+const SEED_ENV: &str = "CLICK_GAME_SEED";
+const RANDOM_SEED_TEXT: &str = "ランダム";
 
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 enum AppState {
     #[default]
     Mainmenu,
     Ingame,
-    Pause,
     Gameover,
     Gameclear,
+    Leaderboard,
+    Settings,
 }
 
-#[derive(Resource, Deref, DerefMut, Debug)]
-struct Config {
-    setup_ingame: bool,
+/// Phase of the round, which lives as long as `AppState::Ingame`.
+///
+/// The round is set up on `OnEnter(AppState::Ingame)` and cleaned up on
+/// `OnExit(AppState::Ingame)`, so pausing and resuming never repeat either.
+#[derive(SubStates, Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[source(AppState = AppState::Ingame)]
+enum Round {
+    #[default]
+    Playing,
+    Paused,
+    Settings,
+}
+
+/// Rules of a round, each with its own leaderboard.
+#[derive(Resource, Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+enum GameMode {
+    /// Pop every ball of the level before the time runs out.
+    #[default]
+    Classic,
+    /// Survive balls spawning ever faster until the field overflows.
+    Endless,
+    /// Pop every ball as fast as possible, without a time limit.
+    TimeAttack,
+    /// Pop only the balls of the target color before the time runs out.
+    ColorMatch,
+    /// Pop the balls in the order of their numbers before the time runs out.
+    Sequence,
+    /// Two players race to pop the most balls before the time runs out.
+    Versus,
+}
+
+impl GameMode {
+    const ALL: [GameMode; 6] = [
+        GameMode::Classic,
+        GameMode::Endless,
+        GameMode::TimeAttack,
+        GameMode::ColorMatch,
+        GameMode::Sequence,
+        GameMode::Versus,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            GameMode::Classic => "classic",
+            GameMode::Endless => "endless",
+            GameMode::TimeAttack => "timeattack",
+            GameMode::ColorMatch => "colormatch",
+            GameMode::Sequence => "sequence",
+            GameMode::Versus => "versus",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            GameMode::Classic => "クラシック",
+            GameMode::Endless => "エンドレス",
+            GameMode::TimeAttack => "タイムアタック",
+            GameMode::ColorMatch => "いろあわせ",
+            GameMode::Sequence => "じゅんばん",
+            GameMode::Versus => "ふたりたいせん",
+        }
+    }
+
+    /// Whether the round ends when the level's time limit runs out.
+    fn has_time_limit(self) -> bool {
+        matches!(self, GameMode::Classic | GameMode::ColorMatch | GameMode::Sequence | GameMode::Versus)
+    }
+}
+
+/// Who made a click: player one with the mouse, player two with the
+/// reticle in the two-player mode, and player one in every other mode.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+enum Player {
+    #[default]
+    One,
+    Two,
+}
+
+impl Player {
+    const ALL: [Player; 2] = [Player::One, Player::Two];
+
+    fn index(self) -> usize {
+        match self {
+            Player::One => 0,
+            Player::Two => 1,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Player::One => "1P",
+            Player::Two => "2P",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Player::One => Color::srgb(0.4, 0.7, 1.0),
+            Player::Two => Color::srgb(1.0, 0.8, 0.3),
+        }
+    }
 }
 
 #[derive(Resource, Deref, DerefMut, Debug)]
 struct BallCount(usize);
 
+/// Points of the current round, including the time bonus once cleared.
+#[derive(Resource, Default, Debug)]
+struct Score {
+    points: u32,
+    time_bonus: u32,
+}
+
 #[derive(Resource)]
 struct GameTimer(Timer);
 
+impl GameTimer {
+    /// Seconds since the round started, rounded to hundredths.
+    fn clear_time(&self) -> f32 {
+        (self.0.elapsed_secs() * 100.0).round() / 100.0
+    }
+}
+
+/// Seed of the ball layout and motion of the current round.
+///
+/// Pinned with `--seed <n>`, the `CLICK_GAME_SEED` environment variable,
+/// the main menu or a replay, and then kept for every round; otherwise
+/// every round draws a fresh one.
+#[derive(Resource, Deref, Debug, Clone, Copy, PartialEq, Eq)]
+struct Seed {
+    #[deref]
+    value: u64,
+    pinned: bool,
+}
+
+impl Seed {
+    fn pinned(value: u64) -> Self {
+        Self { value, pinned: true }
+    }
+
+    fn random() -> Self {
+        Self { value: rand::thread_rng().gen(), pinned: false }
+    }
+
+    /// Seed of the next round: this one when pinned, a fresh one otherwise.
+    fn next(self) -> Self {
+        if self.pinned { self } else { Self::random() }
+    }
+
+    /// The pinned seed, or that the next round draws one.
+    fn label(self) -> String {
+        if self.pinned { self.value.to_string() } else { RANDOM_SEED_TEXT.to_string() }
+    }
+}
+
+/// Random number generator for gameplay, reseeded from `Seed` every round.
+#[derive(Resource, Deref, DerefMut)]
+struct GameRng(StdRng);
+
+impl GameRng {
+    fn new(seed: Seed) -> Self {
+        Self(StdRng::seed_from_u64(*seed))
+    }
+}
+
 #[derive(Resource, Deref)]
 struct ClickSound(Handle<AudioSource>);
 
+/// A click in world coordinates and the player who made it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Click {
+    pos: Vec2,
+    player: Player,
+}
+
+/// A click of player one, like every mouse click.
+impl From<Vec2> for Click {
+    fn from(pos: Vec2) -> Self {
+        Self { pos, player: Player::One }
+    }
+}
+
+/// Left clicks of the current frame.
+///
+/// Filled from the mouse and the reticle by the ingame picking, or pushed
+/// directly when the ingame plugins run headless. Cleared at the end of every frame.
+#[derive(Resource, Deref, DerefMut, Default, Debug)]
+struct ClickInput(Vec<Click>);
+
 fn main() {
+    let seed = seed_from_args().map_or_else(Seed::random, Seed::pinned);
+    println!("main: seed {}", *seed);
+
     App::new()
         .add_plugins(DefaultPlugins
             .set(WindowPlugin {
                 primary_window: Some(Window {
                     resolution: WINDOW_SIZE.into(),
+                    resizable: true,
                     title: GAMETITLE.to_string(),
                     ..Default::default()
                 }),
@@ -64,20 +266,43 @@ fn main() {
         .init_state::<AppState>()
         .insert_resource(ClearColor(BACKGROUND_COLOR))
         .insert_resource(Time::<Fixed>::from_seconds(1.0 / 60.0))
-        .insert_resource(Config { setup_ingame: true })
         .insert_resource(BallCount(BALL_COUNT))
+        .init_resource::<GameMode>()
+        .insert_resource(seed)
+        .insert_resource(playarea::Letterbox::from_args())
+        .insert_resource(GameRng::new(seed))
         .insert_resource(GameTimer(
             Timer::from_seconds(GAMETIME_LIMIT, TimerMode::Once)
         ))
         .add_systems(Startup, setup)
         .add_systems(Update, update)
+        .add_plugins(actions::ActionsPlugin)
         .add_plugins(mainmenu::MainmenuPlugin)
         .add_plugins(ingame::IngamePlugin)
         .add_plugins(gameover::GameoverPlugin)
         .add_plugins(gameclear::GameclearPlugin)
+        .add_plugins(highscore::HighscorePlugin)
+        .add_plugins(leaderboard::LeaderboardPlugin)
+        .add_plugins(level::LevelPlugin)
+        .add_plugins(playarea::PlayAreaPlugin)
+        .add_plugins(replay::ReplayPlugin)
+        .add_plugins(settings::SettingsPlugin)
         .run();
 }
 
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args().skip_while(|arg| arg != SEED_ARG).skip(1);
+    let seed = args.next().or_else(|| std::env::var(SEED_ENV).ok())?;
+
+    match seed.parse() {
+        Ok(seed) => Some(seed),
+        Err(err) => {
+            eprintln!("main: ignored invalid seed {:?}: {}", seed, err);
+            None
+        }
+    }
+}
+
 fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -102,10 +327,12 @@ fn setup(
 
 fn update(
     mut commands: Commands,
-    mouse_events: Res<ButtonInput<MouseButton>>,
+    actions: actions::ActionInput,
+    touches: Res<Touches>,
     sound: Res<ClickSound>,
 ) {
-    if !mouse_events.just_pressed(MouseButton::Left) { return }
+    let clicked = actions.just_pressed(actions::Action::Pop) || actions.just_pressed(actions::Action::Start);
+    if !clicked && !touches.any_just_pressed() { return }
     // play click sound
     commands.spawn(AudioBundle {
         source: sound.clone(),
@@ -1,14 +1,22 @@
 use bevy::{
     prelude::*,
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
 };
 
 use crate::{
     GAMETITLE,
-    WINDOW_SIZE,
     PATH_FONT,
     PATH_IMAGE_MAINMENU,
     AppState,
+    GameMode,
+    Seed,
+    actions::{Action, ActionInput, ActionMap},
+    difficulty::Difficulty,
+    playarea::PlayArea,
 };
 
 const GAMETITLE_SIZE: f32 = 24.0;
@@ -18,19 +26,46 @@ const CLICKSTART_COLOR: Color = Color::srgb(0.8, 0.8, 0.8);
 const BOARD_SIZE: Vec2 = Vec2::new(320.0, 240.0);
 const BOARD_COLOR: Color = Color::srgb(0.2, 0.2, 0.2);
 const TEXT_SIZE: f32 = 20.0;
+const MODE_TEXT: &str = "モード: ";
+const DIFFICULTY_TEXT: &str = "むずかしさ: ";
+const SEED_TEXT: &str = "シード: ";
+const SEED_SIZE: f32 = 16.0;
+const SEED_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
 
 #[derive(Component)]
 struct Mainmenu;
 
+/// Mode, difficulty and seed lines, tappable on touch screens: the mode and
+/// difficulty go to the next one, the seed goes back to a fresh one every round.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum MainmenuButton {
+    Mode,
+    Difficulty,
+    Seed,
+}
+
+#[derive(Component)]
+struct SeedText;
+
+#[derive(Component)]
+struct ModeText;
+
+#[derive(Component)]
+struct DifficultyText;
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+    (seed, action_map): (Res<Seed>, Res<ActionMap>),
+    (mode, difficulty): (Res<GameMode>, Res<Difficulty>),
 ) {
     println!("mainmenu: setup");
     // game title
-    let top = Val::Px(WINDOW_SIZE.y / 2.0 - GAMETITLE_SIZE / 2.0 - BOARD_SIZE.y / 4.0);
+    let top = Val::Px(play_area.size.y / 2.0 - GAMETITLE_SIZE / 2.0 - BOARD_SIZE.y / 4.0);
 
     commands.spawn((
         TextBundle::from_section(
@@ -50,8 +85,94 @@ fn setup(
         Mainmenu,
     ))
     .insert(Name::new("gametitle"));
+    // mode
+    let top = Val::Px(play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + BOARD_SIZE.y / 4.0 - TEXT_SIZE * 1.5 - SEED_SIZE * 1.5);
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                MODE_TEXT,
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: CLICKSTART_COLOR,
+                },
+            ),
+            TextSection::new(
+                format!("< {} >", mode.label()),
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: CLICKSTART_COLOR,
+                },
+            ),
+            TextSection::new(
+                format!(" {} {}", action_map.hint(Action::PreviousMode), action_map.hint(Action::NextMode)),
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: SEED_COLOR,
+                },
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top,
+            ..Default::default()
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        MainmenuButton::Mode,
+        ModeText,
+        Mainmenu,
+    ))
+    .insert(Name::new("mode"));
+    // difficulty
+    let top = Val::Px(play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + BOARD_SIZE.y / 4.0 - TEXT_SIZE * 1.5);
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                DIFFICULTY_TEXT,
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: CLICKSTART_COLOR,
+                },
+            ),
+            TextSection::new(
+                format!("< {} >", difficulty.label()),
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: CLICKSTART_COLOR,
+                },
+            ),
+            TextSection::new(
+                format!(" {} {}", action_map.hint(Action::PreviousDifficulty), action_map.hint(Action::NextDifficulty)),
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: SEED_COLOR,
+                },
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top,
+            ..Default::default()
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        MainmenuButton::Difficulty,
+        DifficultyText,
+        Mainmenu,
+    ))
+    .insert(Name::new("difficulty"));
     // click start
-    let top = Val::Px(WINDOW_SIZE.y / 2.0 - TEXT_SIZE / 2.0 + BOARD_SIZE.y / 4.0);
+    let top = Val::Px(play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + BOARD_SIZE.y / 4.0);
 
     commands.spawn((
         TextBundle::from_section(
@@ -71,6 +192,104 @@ fn setup(
         Mainmenu,
     ))
     .insert(Name::new("clickstart"));
+    // seed
+    let top = Val::Px(play_area.size.y / 2.0 - SEED_SIZE / 2.0 + BOARD_SIZE.y / 4.0 + TEXT_SIZE * 1.5);
+
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                SEED_TEXT,
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: SEED_COLOR,
+                },
+            ),
+            TextSection::new(
+                seed.label(),
+                TextStyle {
+                    font: asset_server.load(PATH_FONT),
+                    font_size: SEED_SIZE,
+                    color: SEED_COLOR,
+                },
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top,
+            ..Default::default()
+        })
+        .with_background_color(BUTTON_COLOR),
+        Interaction::default(),
+        MainmenuButton::Seed,
+        SeedText,
+        Mainmenu,
+    ))
+    .insert(Name::new("seed"));
+    // leaderboard
+    let top = Val::Px(play_area.size.y / 2.0 - SEED_SIZE / 2.0 + BOARD_SIZE.y / 4.0 + TEXT_SIZE * 1.5 + SEED_SIZE * 1.5);
+
+    commands.spawn((
+        TextBundle::from_section(
+            format!("{}: {}", Action::Leaderboard.label(), action_map.hint(Action::Leaderboard)),
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: SEED_SIZE,
+                color: SEED_COLOR,
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top,
+            ..Default::default()
+        }),
+        Mainmenu,
+    ))
+    .insert(Name::new("leaderboard"));
+    // settings
+    let top = Val::Px(play_area.size.y / 2.0 - SEED_SIZE / 2.0 + BOARD_SIZE.y / 4.0 + TEXT_SIZE * 1.5 + SEED_SIZE * 3.0);
+
+    commands.spawn((
+        TextBundle::from_section(
+            format!("{}: {}", Action::Settings.label(), action_map.hint(Action::Settings)),
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: SEED_SIZE,
+                color: SEED_COLOR,
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top,
+            ..Default::default()
+        }),
+        Mainmenu,
+    ))
+    .insert(Name::new("settings"));
+    // replay
+    let top = Val::Px(play_area.size.y / 2.0 - SEED_SIZE / 2.0 + BOARD_SIZE.y / 4.0 + TEXT_SIZE * 1.5 + SEED_SIZE * 4.5);
+
+    commands.spawn((
+        TextBundle::from_section(
+            format!("{}: {}", Action::Replay.label(), action_map.hint(Action::Replay)),
+            TextStyle {
+                font: asset_server.load(PATH_FONT),
+                font_size: SEED_SIZE,
+                color: SEED_COLOR,
+            }
+        )
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top,
+            ..Default::default()
+        }),
+        Mainmenu,
+    ))
+    .insert(Name::new("replay"));
     // board
     commands.spawn((
         MaterialMesh2dBundle {
@@ -94,17 +313,131 @@ fn setup(
 }
 
 fn update(
-    mut commands: Commands,
     mut next_state: ResMut<NextState<AppState>>,
-    mouse_events: Res<ButtonInput<MouseButton>>,
-    query: Query<Entity, With<Mainmenu>>,
+    touches: Res<Touches>,
+    button_query: Query<&Interaction, With<MainmenuButton>>,
+    actions: ActionInput,
 ) {
-    if !mouse_events.just_pressed(MouseButton::Left) { return }
+    // a click or tap on a button only changes what it shows
+    let on_button = button_query.iter().any(|interaction| *interaction == Interaction::Pressed);
 
+    if (actions.just_pressed(Action::Start) || touches.any_just_pressed()) && !on_button {
+        println!("mainmenu: moved state to Ingame from Mainmenu");
+        next_state.set(AppState::Ingame);
+    } else if actions.just_pressed(Action::Leaderboard) {
+        println!("mainmenu: moved state to Leaderboard from Mainmenu");
+        next_state.set(AppState::Leaderboard);
+    } else if actions.just_pressed(Action::Settings) {
+        println!("mainmenu: moved state to Settings from Mainmenu");
+        next_state.set(AppState::Settings);
+    }
+}
+
+fn despawn(
+    mut commands: Commands,
+    query: Query<Entity, With<Mainmenu>>,
+) {
     println!("mainmenu: despawn");
     for entity in query.iter() { commands.entity(entity).despawn() }
-    println!("mainmenu: moved state to Ingame from Mainmenu");
-    next_state.set(AppState::Ingame);
+}
+
+/// Whether `button` was clicked or tapped this frame.
+fn tapped(
+    button_query: &Query<(&Interaction, &MainmenuButton), Changed<Interaction>>,
+    button: MainmenuButton,
+) -> bool {
+    button_query.iter().any(|(interaction, pressed)| *interaction == Interaction::Pressed && *pressed == button)
+}
+
+fn edit_seed(
+    mut query: Query<&mut Text, With<SeedText>>,
+    mut seed: ResMut<Seed>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    button_query: Query<(&Interaction, &MainmenuButton), Changed<Interaction>>,
+    actions: ActionInput,
+) {
+    // digits are read as typed text, so the number row and the numpad both work
+    let digits: Vec<u32> = keyboard_events.read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .filter_map(|event| match &event.logical_key {
+            Key::Character(chars) => Some(chars.chars().filter_map(|c| c.to_digit(10)).collect::<Vec<_>>()),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+
+    // typing pins a seed, erasing its last digit draws one every round again
+    for digit in digits {
+        let typed = if seed.pinned { **seed } else { 0 };
+        let Some(new_seed) = typed.checked_mul(10)
+            .and_then(|seed| seed.checked_add(digit as u64)) else { continue };
+        *seed = Seed::pinned(new_seed);
+        println!("mainmenu: changed seed to {}", seed.label());
+    }
+    if actions.just_pressed(Action::EraseSeedDigit) && seed.pinned {
+        *seed = if **seed < 10 { Seed::random() } else { Seed::pinned(**seed / 10) };
+        println!("mainmenu: changed seed to {}", seed.label());
+    }
+    if tapped(&button_query, MainmenuButton::Seed) && seed.pinned {
+        *seed = Seed::random();
+        println!("mainmenu: changed seed to {}", seed.label());
+    }
+
+    if !seed.is_changed() { return }
+    let Ok(mut text) = query.get_single_mut() else { return };
+    text.sections[1].value = seed.label();
+}
+
+fn change_mode(
+    mut query: Query<&mut Text, With<ModeText>>,
+    mut mode: ResMut<GameMode>,
+    button_query: Query<(&Interaction, &MainmenuButton), Changed<Interaction>>,
+    actions: ActionInput,
+) {
+    let index = GameMode::ALL.iter().position(|game_mode| *game_mode == *mode).unwrap_or(0);
+    let len = GameMode::ALL.len();
+
+    let game_mode = if actions.just_pressed(Action::PreviousMode) {
+        Some(GameMode::ALL[(index + len - 1) % len])
+    } else if actions.just_pressed(Action::NextMode) || tapped(&button_query, MainmenuButton::Mode) {
+        Some(GameMode::ALL[(index + 1) % len])
+    } else {
+        None
+    };
+    if let Some(game_mode) = game_mode {
+        println!("mainmenu: changed mode to {:?}", game_mode);
+        *mode = game_mode;
+    }
+
+    if !mode.is_changed() { return }
+    let Ok(mut text) = query.get_single_mut() else { return };
+    text.sections[1].value = format!("< {} >", mode.label());
+}
+
+fn change_difficulty(
+    mut query: Query<&mut Text, With<DifficultyText>>,
+    mut difficulty: ResMut<Difficulty>,
+    button_query: Query<(&Interaction, &MainmenuButton), Changed<Interaction>>,
+    actions: ActionInput,
+) {
+    let index = Difficulty::ALL.iter().position(|preset| *preset == *difficulty).unwrap_or(0);
+    let len = Difficulty::ALL.len();
+
+    let preset = if actions.just_pressed(Action::PreviousDifficulty) {
+        Some(Difficulty::ALL[(index + len - 1) % len])
+    } else if actions.just_pressed(Action::NextDifficulty) || tapped(&button_query, MainmenuButton::Difficulty) {
+        Some(Difficulty::ALL[(index + 1) % len])
+    } else {
+        None
+    };
+    if let Some(preset) = preset {
+        println!("mainmenu: changed difficulty to {:?}", preset);
+        *difficulty = preset;
+    }
+
+    if !difficulty.is_changed() { return }
+    let Ok(mut text) = query.get_single_mut() else { return };
+    text.sections[1].value = format!("< {} >", difficulty.label());
 }
 
 pub struct MainmenuPlugin;
@@ -113,7 +446,13 @@ impl Plugin for MainmenuPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems(OnEnter(AppState::Mainmenu), setup)
-            .add_systems(Update, update.run_if(in_state(AppState::Mainmenu)))
+            .add_systems(Update, (
+                update,
+                edit_seed,
+                change_mode,
+                change_difficulty,
+            ).run_if(in_state(AppState::Mainmenu)))
+            .add_systems(OnExit(AppState::Mainmenu), despawn)
         ;
     }
 }
@@ -0,0 +1,145 @@
+use bevy::{
+    prelude::*,
+    render::camera::Viewport,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use crate::WINDOW_SIZE;
+
+const LETTERBOX_ARG: &str = "--letterbox";
+const LETTERBOX_ENV: &str = "CLICK_GAME_LETTERBOX";
+
+/// Region of the world the game is played in, centered on the origin.
+///
+/// Follows the window size, or keeps `WINDOW_SIZE` scaled to fit the window
+/// when letterboxed.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct PlayArea {
+    /// Size in world units.
+    pub size: Vec2,
+    /// Logical window pixels per world unit.
+    pub scale: f32,
+    /// Top left corner in logical window pixels.
+    pub origin: Vec2,
+}
+
+impl Default for PlayArea {
+    fn default() -> Self {
+        Self { size: WINDOW_SIZE, scale: 1.0, origin: Vec2::ZERO }
+    }
+}
+
+impl PlayArea {
+    /// Play area of a window of `window_size` logical pixels.
+    pub fn fit(window_size: Vec2, letterbox: bool) -> Self {
+        if !letterbox {
+            return Self { size: window_size, ..Default::default() }
+        }
+
+        let scale = (window_size / WINDOW_SIZE).min_element();
+        let origin = (window_size - WINDOW_SIZE * scale) / 2.0;
+        Self { size: WINDOW_SIZE, scale, origin }
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        self.size / 2.0
+    }
+
+    /// `pos` moved inside the area far enough for a ball of diameter `size`.
+    pub fn clamp(&self, pos: Vec2, size: f32) -> Vec2 {
+        let bound = (self.half_size() - size / 2.0).max(Vec2::ZERO);
+        pos.clamp(-bound, bound)
+    }
+}
+
+/// Scale the fixed size play area to fit the window, with bars around it.
+///
+/// Set with `--letterbox` or the `CLICK_GAME_LETTERBOX` environment variable.
+#[derive(Resource, Deref, Default, Debug, Clone, Copy)]
+pub struct Letterbox(pub bool);
+
+impl Letterbox {
+    pub fn from_args() -> Self {
+        let arg = std::env::args().any(|arg| arg == LETTERBOX_ARG);
+        let env = std::env::var(LETTERBOX_ENV).is_ok_and(|value| !matches!(value.as_str(), "" | "0" | "false"));
+        Self(arg || env)
+    }
+}
+
+/// Letterboxes the play area whatever `Letterbox` says, while a replay
+/// recorded at `WINDOW_SIZE` plays back.
+#[derive(Resource, Default, Debug)]
+pub struct ForceLetterbox;
+
+fn fit_window(
+    mut play_area: ResMut<PlayArea>,
+    mut ui_scale: ResMut<UiScale>,
+    mut camera_query: Query<(&mut Camera, &mut OrthographicProjection)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    (letterbox, force_letterbox): (Res<Letterbox>, Option<Res<ForceLetterbox>>),
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    // a minimized window has no area to fit
+    if window.width() <= 0.0 || window.height() <= 0.0 { return }
+    let letterbox = **letterbox || force_letterbox.is_some();
+    let area = PlayArea::fit(window.size(), letterbox);
+
+    if *play_area != area {
+        println!("playarea: fit {} to window {}", area.size, window.size());
+        *play_area = area;
+    }
+    ui_scale.0 = area.scale;
+
+    let Ok((mut camera, mut projection)) = camera_query.get_single_mut() else { return };
+    projection.scale = 1.0 / area.scale;
+    camera.viewport = letterbox.then(|| Viewport {
+        physical_position: (area.origin * window.scale_factor()).as_uvec2(),
+        physical_size: (area.size * area.scale * window.scale_factor()).as_uvec2().max(UVec2::ONE),
+        ..Default::default()
+    });
+}
+
+pub struct PlayAreaPlugin;
+
+impl Plugin for PlayAreaPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PlayArea>()
+            .add_systems(PostStartup, fit_window)
+            .add_systems(PreUpdate, fit_window.run_if(on_event::<WindowResized>()
+                .or_else(resource_added::<ForceLetterbox>)
+                .or_else(resource_removed::<ForceLetterbox>())))
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_area_follows_the_window() {
+        let area = PlayArea::fit(Vec2::new(1280.0, 720.0), false);
+
+        assert_eq!(area.size, Vec2::new(1280.0, 720.0));
+        assert_eq!(area.half_size(), Vec2::new(640.0, 360.0));
+        assert_eq!(area.scale, 1.0);
+    }
+
+    #[test]
+    fn letterbox_keeps_the_aspect_ratio() {
+        let area = PlayArea::fit(WINDOW_SIZE * Vec2::new(3.0, 2.0), true);
+
+        assert_eq!(area.size, WINDOW_SIZE);
+        assert_eq!(area.scale, 2.0);
+        assert_eq!(area.origin, Vec2::new(WINDOW_SIZE.x / 2.0, 0.0));
+    }
+
+    #[test]
+    fn clamp_keeps_balls_inside() {
+        let area = PlayArea::fit(Vec2::new(200.0, 100.0), false);
+
+        assert_eq!(area.clamp(Vec2::new(500.0, -500.0), 20.0), Vec2::new(90.0, -40.0));
+        assert_eq!(area.clamp(Vec2::new(10.0, 10.0), 20.0), Vec2::new(10.0, 10.0));
+    }
+}
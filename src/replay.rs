@@ -0,0 +1,219 @@
+//! Replay files: the clicks of a round with the settings it started from,
+//! saved from the results screens and played back from the title screen.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    PATH_FONT,
+    WINDOW_SIZE,
+    storage,
+    AppState,
+    GameMode,
+    Player,
+    Seed,
+    actions::{Action, ActionInput, ActionMap},
+    difficulty::Difficulty,
+    highscore::NameEntry,
+    ingame::{Playback, Recording},
+    level::LevelProgress,
+    playarea::{ForceLetterbox, PlayArea},
+};
+
+const STORAGE_KEY: &str = "replay";
+const SAVE_TEXT: &str = "リプレイをほぞん: ";
+const SAVED_TEXT: &str = "リプレイをほぞんしました";
+const SEED_TEXT: &str = " / シード: ";
+const TEXT_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+const TEXT_SIZE: f32 = 16.0;
+const TEXT_PADDING: f32 = 50.0;
+
+/// A click of a replay, at the fixed step of the round it was made in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ReplayClick {
+    pub tick: u64,
+    /// World position of the click.
+    pub pos: Vec2,
+    pub player: Player,
+}
+
+/// How a round ended, to check a playback against.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayResult {
+    pub cleared: bool,
+    pub ball_count: usize,
+    pub points: u32,
+}
+
+/// Everything needed to play a round again: the settings it started from
+/// and every click that reached the balls.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    /// Number of the level in the level sequence, from 1.
+    pub level: usize,
+    /// Size of the play area in world units, where the balls bounce off.
+    /// Replays saved before it was kept are taken as `WINDOW_SIZE`.
+    #[serde(default = "default_play_area")]
+    pub play_area: Vec2,
+    pub clicks: Vec<ReplayClick>,
+    /// `None` while the round is still being played.
+    pub result: Option<ReplayResult>,
+}
+
+fn default_play_area() -> Vec2 {
+    WINDOW_SIZE
+}
+
+/// Whether a replay recorded in a play area of `size` plays back in
+/// `play_area` as it is, `Some(false)`, or letterboxed, `Some(true)`; `None`
+/// if the size cannot be had in this window.
+fn needs_letterbox(size: Vec2, play_area: &PlayArea) -> Option<bool> {
+    if size == play_area.size { return Some(false) }
+    (size == WINDOW_SIZE).then_some(true)
+}
+
+#[derive(Component)]
+struct ReplayUi;
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+    action_map: Res<ActionMap>,
+    recording: Res<Recording>,
+) {
+    let top = play_area.size.y / 2.0 - TEXT_SIZE / 2.0 + TEXT_PADDING * 3.25;
+    let text_style = TextStyle {
+        font: asset_server.load(PATH_FONT),
+        font_size: TEXT_SIZE,
+        color: TEXT_COLOR,
+    };
+    // the seed of the round, to play it again from the main menu
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(format!("{}{}", SAVE_TEXT, action_map.hint(Action::Replay)), text_style.clone()),
+            TextSection::new(format!("{}{}", SEED_TEXT, recording.seed), text_style),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Relative,
+            justify_self: JustifySelf::Center,
+            top: Val::Px(top),
+            ..Default::default()
+        }),
+        ReplayUi,
+    ))
+    .insert(Name::new("replay"));
+}
+
+fn save(
+    mut query: Query<&mut Text, With<ReplayUi>>,
+    actions: ActionInput,
+    recording: Res<Recording>,
+) {
+    if !actions.just_pressed(Action::Replay) { return }
+
+    println!("replay: saved {} clicks", recording.clicks.len());
+    storage::save(STORAGE_KEY, &**recording);
+    for mut text in query.iter_mut() { text.sections[0].value = SAVED_TEXT.to_string() }
+}
+
+/// Starts the saved replay with the seed, mode, difficulty, level and play
+/// area it was recorded at.
+fn load(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut progress: ResMut<LevelProgress>,
+    (actions, play_area): (ActionInput, Res<PlayArea>),
+    (mut seed, mut mode, mut difficulty): (ResMut<Seed>, ResMut<GameMode>, ResMut<Difficulty>),
+) {
+    if !actions.just_pressed(Action::Replay) { return }
+    let Some(replay) = storage::load::<Replay>(STORAGE_KEY) else {
+        println!("replay: no saved replay");
+        return
+    };
+    let Some(letterbox) = needs_letterbox(replay.play_area, &play_area) else {
+        println!("replay: cannot play back a play area of {} in {}", replay.play_area, play_area.size);
+        return
+    };
+    // until the playback stops
+    if letterbox { commands.init_resource::<ForceLetterbox>() }
+
+    println!("replay: loaded level {} with seed {}", replay.level, replay.seed);
+    *seed = Seed::pinned(replay.seed);
+    *mode = replay.mode;
+    *difficulty = replay.difficulty;
+    progress.select(replay.level);
+    commands.insert_resource(Playback::new(replay));
+    println!("replay: moved state to Ingame from Mainmenu");
+    next_state.set(AppState::Ingame);
+}
+
+fn despawn(
+    mut commands: Commands,
+    query: Query<Entity, With<ReplayUi>>,
+) {
+    for entity in query.iter() { commands.entity(entity).despawn() }
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(AppState::Gameover), setup)
+            .add_systems(OnEnter(AppState::Gameclear), setup)
+            .add_systems(Update, save
+                .run_if(in_state(AppState::Gameover).or_else(in_state(AppState::Gameclear)))
+                .run_if(not(resource_exists::<NameEntry>)))
+            .add_systems(Update, load.run_if(in_state(AppState::Mainmenu)))
+            .add_systems(OnExit(AppState::Gameover), despawn)
+            .add_systems(OnExit(AppState::Gameclear), despawn)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_survive_saving() {
+        let replay = Replay {
+            seed: 42,
+            mode: GameMode::Versus,
+            difficulty: Difficulty::Hard,
+            level: 2,
+            play_area: Vec2::new(800.0, 600.0),
+            clicks: vec![ReplayClick { tick: 30, pos: Vec2::new(12.5, -40.0), player: Player::Two }],
+            result: Some(ReplayResult { cleared: false, ball_count: 3, points: 120 }),
+        };
+
+        let json = serde_json::to_string(&replay).unwrap();
+        let loaded: Replay = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded, replay);
+        assert!(json.contains("\"versus\""));
+    }
+
+    #[test]
+    fn replays_play_back_only_in_their_play_area() {
+        let window = PlayArea::fit(Vec2::new(1280.0, 720.0), false);
+        let letterboxed = PlayArea::fit(Vec2::new(1280.0, 720.0), true);
+
+        assert_eq!(needs_letterbox(Vec2::new(1280.0, 720.0), &window), Some(false));
+        assert_eq!(needs_letterbox(WINDOW_SIZE, &window), Some(true));
+        assert_eq!(needs_letterbox(WINDOW_SIZE, &letterboxed), Some(false));
+        assert_eq!(needs_letterbox(Vec2::new(800.0, 600.0), &window), None);
+        assert_eq!(needs_letterbox(Vec2::new(800.0, 600.0), &letterboxed), None);
+    }
+
+    #[test]
+    fn old_replays_were_recorded_at_the_window_size() {
+        let replay: Replay = serde_json::from_str(r#"{"seed":1,"mode":"classic","difficulty":"normal","level":1,"clicks":[],"result":null}"#).unwrap();
+
+        assert_eq!(replay.play_area, WINDOW_SIZE);
+    }
+}
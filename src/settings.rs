@@ -0,0 +1,327 @@
+use bevy::prelude::*;
+
+use crate::{
+    BACKGROUND_COLOR,
+    PATH_FONT,
+    AppState,
+    Round,
+    actions::{Action, ActionMap, Binding},
+    playarea::PlayArea,
+};
+
+const SETTINGS_TEXT: &str = "せってい";
+const SETTINGS_SIZE: f32 = 32.0;
+const LISTENING_TEXT: &str = "あたらしいボタンをおしてください";
+const SELECT_TEXT: &str = "えらぶ: Key[↑][↓]";
+const REBIND_TEXT: &str = "へんこう: Key[Enter] / もとにもどす: Key[Delete]";
+const BACKTOTITLE_TEXT: &str = "もどる: Key[Escape]";
+const CURSOR_TEXT: &str = "> ";
+const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const SELECTED_COLOR: Color = Color::srgb(1.0, 0.8, 0.3);
+const TEXT_SIZE: f32 = 20.0;
+const TEXT_PADDING: f32 = 50.0;
+const ROW_SIZE: f32 = 15.0;
+const ROW_HEIGHT: f32 = 16.0;
+
+#[derive(Component)]
+struct SettingsScreen;
+
+#[derive(Component, Deref)]
+struct ActionRow(Action);
+
+#[derive(Component)]
+struct ListeningText;
+
+/// Whether the settings screen is open, from the main menu or a paused round.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct SettingsOpen;
+
+impl ComputedStates for SettingsOpen {
+    type SourceStates = (AppState, Option<Round>);
+
+    fn compute((app_state, round): (AppState, Option<Round>)) -> Option<Self> {
+        (app_state == AppState::Settings || round == Some(Round::Settings)).then_some(SettingsOpen)
+    }
+}
+
+/// Row under the cursor, and whether its next pressed input becomes a binding.
+#[derive(Resource, Default, Debug)]
+struct Selection {
+    index: usize,
+    listening: bool,
+}
+
+fn setup(
+    mut commands: Commands,
+) {
+    println!("settings: setup");
+    commands.insert_resource(Selection::default());
+}
+
+fn spawn_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    play_area: Res<PlayArea>,
+) {
+    let text_style = |font_size, color| TextStyle {
+        font: asset_server.load(PATH_FONT),
+        font_size,
+        color,
+    };
+    // backdrop covering a paused round
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..Default::default()
+            },
+            background_color: BACKGROUND_COLOR.into(),
+            z_index: ZIndex::Global(2),
+            ..Default::default()
+        },
+        SettingsScreen,
+    ))
+    .insert(Name::new("settings"))
+    .with_children(|parent| {
+        // settings
+        parent.spawn(
+            TextBundle::from_section(SETTINGS_TEXT, text_style(SETTINGS_SIZE, TEXT_COLOR))
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    justify_self: JustifySelf::Center,
+                    top: Val::Px(TEXT_PADDING * 0.5),
+                    ..Default::default()
+                })
+        )
+        .insert(Name::new("title"));
+        // actions
+        for (index, action) in Action::ALL.into_iter().enumerate() {
+            parent.spawn((
+                TextBundle::from_section(String::new(), text_style(ROW_SIZE, TEXT_COLOR))
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(TEXT_PADDING * 1.5 + ROW_HEIGHT * index as f32),
+                        left: Val::Px(TEXT_PADDING),
+                        ..Default::default()
+                    }),
+                ActionRow(action),
+            ))
+            .insert(Name::new("action"));
+        }
+        // listening
+        let top = TEXT_PADDING * 1.5 + ROW_HEIGHT * (Action::ALL.len() as f32 + 0.5);
+
+        parent.spawn((
+            TextBundle::from_section(String::new(), text_style(TEXT_SIZE, SELECTED_COLOR))
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    justify_self: JustifySelf::Center,
+                    top: Val::Px(top),
+                    ..Default::default()
+                }),
+            ListeningText,
+        ))
+        .insert(Name::new("listening"));
+        // hints
+        let hints = [
+            (SELECT_TEXT, play_area.size.y - TEXT_PADDING * 2.5),
+            (REBIND_TEXT, play_area.size.y - TEXT_PADDING * 2.0),
+            (BACKTOTITLE_TEXT, play_area.size.y - TEXT_PADDING * 1.5),
+        ];
+
+        for (hint, top) in hints {
+            parent.spawn(
+                TextBundle::from_section(hint, text_style(TEXT_SIZE, TEXT_COLOR))
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        justify_self: JustifySelf::Center,
+                        top: Val::Px(top),
+                        ..Default::default()
+                    })
+            )
+            .insert(Name::new("hint"));
+        }
+    });
+}
+
+fn update(
+    mut next_state: ResMut<NextState<AppState>>,
+    mut next_round: ResMut<NextState<Round>>,
+    mut selection: ResMut<Selection>,
+    mut action_map: ResMut<ActionMap>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    round: Option<Res<State<Round>>>,
+) {
+    if selection.listening { return }
+
+    let len = Action::ALL.len();
+    let action = Action::ALL[selection.index];
+
+    for key in keyboard_input.get_just_pressed() {
+        match key {
+            KeyCode::ArrowUp => selection.index = (selection.index + len - 1) % len,
+            KeyCode::ArrowDown => selection.index = (selection.index + 1) % len,
+            KeyCode::Enter => {
+                println!("settings: listening for {:?}", action);
+                selection.listening = true;
+            },
+            KeyCode::Delete | KeyCode::Backspace => {
+                println!("settings: reset {:?}", action);
+                action_map.reset(action);
+                action_map.save();
+            },
+            // back to the paused round when opened from its pause menu
+            KeyCode::Escape if round.is_some() => {
+                println!("settings: moved round to Paused from Settings");
+                next_round.set(Round::Paused);
+            },
+            KeyCode::Escape => {
+                println!("settings: moved state to Mainmenu from Settings");
+                next_state.set(AppState::Mainmenu);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Binds the next pressed input to the selected action, any key included.
+fn listen(
+    mut selection: ResMut<Selection>,
+    mut action_map: ResMut<ActionMap>,
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_input: ResMut<ButtonInput<MouseButton>>,
+    mut gamepad_input: ResMut<ButtonInput<GamepadButton>>,
+) {
+    // runs before `update`, so the Enter that starts listening is not a binding
+    if !selection.listening { return }
+
+    let key = keyboard_input.get_just_pressed().next().copied();
+    let mouse_button = mouse_input.get_just_pressed().next().copied();
+    let gamepad_button = gamepad_input.get_just_pressed().next().copied();
+    // taken by the binding, so the menu does not act on it in the same frame
+    let binding = if let Some(key) = key {
+        keyboard_input.clear_just_pressed(key);
+        Binding::Key(key)
+    } else if let Some(button) = mouse_button {
+        mouse_input.clear_just_pressed(button);
+        Binding::Mouse(button)
+    } else if let Some(button) = gamepad_button {
+        gamepad_input.clear_just_pressed(button);
+        Binding::Gamepad(button.button_type)
+    } else {
+        return
+    };
+
+    let action = Action::ALL[selection.index];
+    println!("settings: bound {:?} to {:?}", action, binding);
+    action_map.rebind(action, binding);
+    action_map.save();
+    selection.listening = false;
+}
+
+fn update_text(
+    mut row_query: Query<(&mut Text, &ActionRow), Without<ListeningText>>,
+    mut listening_query: Query<&mut Text, With<ListeningText>>,
+    selection: Res<Selection>,
+    action_map: Res<ActionMap>,
+) {
+    if !selection.is_changed() && !action_map.is_changed() { return }
+
+    for (mut text, row) in row_query.iter_mut() {
+        let selected = Action::ALL[selection.index] == **row;
+        let bindings: Vec<String> = action_map.bindings(**row).iter().map(Binding::label).collect();
+        let section = &mut text.sections[0];
+
+        section.value = format!(
+            "{}{}: {}",
+            if selected { CURSOR_TEXT } else { "  " },
+            row.label(),
+            bindings.join(" / "),
+        );
+        section.style.color = if selected { SELECTED_COLOR } else { TEXT_COLOR };
+    }
+    for mut text in listening_query.iter_mut() {
+        text.sections[0].value = if selection.listening { LISTENING_TEXT.to_string() } else { String::new() };
+    }
+}
+
+fn despawn(
+    mut commands: Commands,
+    query: Query<Entity, With<SettingsScreen>>,
+) {
+    println!("settings: despawn");
+    commands.remove_resource::<Selection>();
+    for entity in query.iter() { commands.entity(entity).despawn_recursive() }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_computed_state::<SettingsOpen>()
+            .add_systems(OnEnter(SettingsOpen), (
+                setup,
+                spawn_ui.run_if(resource_exists::<AssetServer>),
+            ))
+            .add_systems(Update, (
+                listen,
+                update,
+                update_text,
+            ).chain().run_if(in_state(SettingsOpen)))
+            .add_systems(OnExit(SettingsOpen), despawn)
+        ;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+
+    fn settings_app() -> App {
+        // rebinding saves, away from the bindings of the player
+        std::env::set_var("CLICK_GAME_SAVE_DIR", std::env::temp_dir().join("ittoku-click-game-tests"));
+        let mut app = App::new();
+
+        app
+            .add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_state(AppState::Settings)
+            .add_sub_state::<Round>()
+            .init_resource::<ActionMap>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<ButtonInput<GamepadButton>>()
+            .add_plugins(SettingsPlugin);
+        app.update();
+        app
+    }
+
+    fn press_key(app: &mut App, key: KeyCode) {
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+        app.update();
+
+        let mut keyboard_input = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keyboard_input.release(key);
+        keyboard_input.clear();
+    }
+
+    #[test]
+    fn keys_of_the_menu_can_be_bound_too() {
+        for key in [KeyCode::Delete, KeyCode::Enter, KeyCode::ArrowDown, KeyCode::Escape] {
+            let mut app = settings_app();
+
+            press_key(&mut app, KeyCode::Enter);
+            press_key(&mut app, key);
+            app.update();
+
+            let bindings = app.world().resource::<ActionMap>().bindings(Action::Pop);
+            assert!(bindings.contains(&Binding::Key(key)), "{:?}", key);
+            let selection = app.world().resource::<Selection>();
+            assert_eq!((selection.index, selection.listening), (0, false), "{:?}", key);
+            assert_eq!(*app.world().resource::<State<AppState>>().get(), AppState::Settings);
+        }
+    }
+}
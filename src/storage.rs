@@ -0,0 +1,80 @@
+//! Key-value storage that survives restarts.
+//!
+//! Native builds write one JSON file per key into `CLICK_GAME_SAVE_DIR`
+//! (default `save/`), the wasm build uses the browser's `localStorage`.
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_DIR: &str = "save";
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_DIR_ENV: &str = "CLICK_GAME_SAVE_DIR";
+#[cfg(target_arch = "wasm32")]
+const KEY_PREFIX: &str = "ittoku-click-game/";
+
+/// Reads the value stored under `key`, `None` if missing or unreadable.
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    let json = read(key)?;
+
+    match serde_json::from_str(&json) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            eprintln!("storage: ignored broken {}: {}", key, err);
+            None
+        }
+    }
+}
+
+/// Stores `value` under `key`, logging instead of failing.
+pub fn save<T: Serialize>(key: &str, value: &T) {
+    let json = match serde_json::to_string_pretty(value) {
+        Ok(json) => json,
+        Err(err) => {
+            eprintln!("storage: could not serialize {}: {}", key, err);
+            return
+        }
+    };
+
+    if let Err(err) = write(key, &json) {
+        eprintln!("storage: could not save {}: {}", key, err);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn path(key: &str) -> std::path::PathBuf {
+    let dir = std::env::var(SAVE_DIR_ENV).unwrap_or_else(|_| SAVE_DIR.to_string());
+    std::path::Path::new(&dir).join(format!("{}.json", key))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read(key: &str) -> Option<String> {
+    std::fs::read_to_string(path(key)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write(key: &str, json: &str) -> Result<(), String> {
+    let path = path(key);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    std::fs::write(path, json).map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read(key: &str) -> Option<String> {
+    local_storage()?.get_item(&format!("{}{}", KEY_PREFIX, key)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write(key: &str, json: &str) -> Result<(), String> {
+    let storage = local_storage().ok_or("localStorage is not available")?;
+    storage
+        .set_item(&format!("{}{}", KEY_PREFIX, key), json)
+        .map_err(|err| format!("{:?}", err))
+}